@@ -0,0 +1,286 @@
+//! # Domo Pitchfork Derive
+//!
+//! Derive macros for `domo_pitchfork`. Enable the `derive` feature of `domo_pitchfork`
+//! instead of depending on this crate directly.
+//!
+//! ## `#[derive(DomoSchema)]`
+//!
+//! Implements `domo_pitchfork::domo::dataset::DomoSchema` for a struct with named fields.
+//! Each field becomes a column, in field order, with its Domo data type picked from the field's type:
+//!
+//! | Rust type | Domo type |
+//! |-----------|-----------|
+//! | `String`, `&str`, `char`, `bool` | `STRING` |
+//! | `i8` - `i128`, `u8` - `u128`, `isize`, `usize` | `LONG` |
+//! | `f32`, `f64` | `DOUBLE` |
+//! | `chrono::NaiveDate` | `DATE` |
+//! | `chrono::NaiveDateTime`, `chrono::DateTime<Tz>` | `DATETIME` |
+//!
+//! `Option<T>` and `Box<T>` use the type of `T`. Other types need an explicit
+//! `#[domo(type = "STRING")]` attribute. Column names follow serde's `rename`, `rename_all`,
+//! `skip` and `skip_serializing` attributes so the schema matches the csv written by serde.
+#![warn(rust_2018_idioms)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident, Lit,
+    Meta, NestedMeta, PathArguments, Type,
+};
+
+#[proc_macro_derive(DomoSchema, attributes(domo))]
+pub fn derive_domo_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "DomoSchema can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "DomoSchema can only be derived for structs",
+            ))
+        }
+    };
+    let rename_all = container_rename_all(&input.attrs)?;
+
+    let mut columns = Vec::new();
+    for field in fields {
+        let serde = field_serde_attrs(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let name = match serde.rename {
+            Some(name) => name,
+            None => {
+                let name = ident.to_string();
+                let name = name.trim_start_matches("r#");
+                match &rename_all {
+                    Some(rule) => apply_rename_rule(rule, name),
+                    None => name.to_string(),
+                }
+            }
+        };
+        let domo_type = match domo_type_attr(&field.attrs)? {
+            Some(t) => t,
+            None => domo_type_for(&field.ty).ok_or_else(|| {
+                Error::new_spanned(
+                    &field.ty,
+                    "can't map this type to a Domo data type, add #[domo(type = \"STRING\")] (or LONG, DECIMAL, DOUBLE, DATE, DATETIME)",
+                )
+            })?,
+        };
+        let variant = Ident::new(domo_type, Span::call_site());
+        columns.push(quote! {
+            ::domo_pitchfork::domo::dataset::Column {
+                column_type: ::domo_pitchfork::domo::dataset::DomoDataType::#variant,
+                name: #name.to_string(),
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::domo_pitchfork::domo::dataset::DomoSchema for #ident #ty_generics #where_clause {
+            fn domo_schema() -> ::domo_pitchfork::domo::dataset::Schema {
+                ::domo_pitchfork::domo::dataset::Schema {
+                    columns: vec![#(#columns),*],
+                }
+            }
+        }
+    })
+}
+
+const DOMO_TYPES: &[&str] = &["STRING", "LONG", "DECIMAL", "DOUBLE", "DATE", "DATETIME"];
+
+/// Domo data type for a Rust type, looking through `Option`, `Box` and references.
+fn domo_type_for(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Reference(r) => domo_type_for(&r.elem),
+        Type::Group(g) => domo_type_for(&g.elem),
+        Type::Paren(p) => domo_type_for(&p.elem),
+        Type::Path(p) if p.qself.is_none() => {
+            let seg = p.path.segments.last()?;
+            match seg.ident.to_string().as_str() {
+                "String" | "str" | "char" | "bool" => Some("STRING"),
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+                | "u128" | "usize" => Some("LONG"),
+                "f32" | "f64" => Some("DOUBLE"),
+                "NaiveDate" => Some("DATE"),
+                "NaiveDateTime" | "DateTime" => Some("DATETIME"),
+                "Option" | "Box" => match &seg.arguments {
+                    PathArguments::AngleBracketed(args) => match args.args.first()? {
+                        GenericArgument::Type(inner) => domo_type_for(inner),
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `#[domo(type = "...")]` override on a field.
+fn domo_type_attr(attrs: &[Attribute]) -> Result<Option<&'static str>, Error> {
+    let mut domo_type = None;
+    for meta in nested_metas(attrs, "domo")? {
+        match &meta {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("type") => match &nv.lit {
+                Lit::Str(s) => {
+                    let value = s.value();
+                    match DOMO_TYPES.iter().find(|t| **t == value) {
+                        Some(t) => domo_type = Some(*t),
+                        None => {
+                            return Err(Error::new_spanned(
+                                s,
+                                format!(
+                                    "unknown Domo data type, expected one of {}",
+                                    DOMO_TYPES.join(", ")
+                                ),
+                            ))
+                        }
+                    }
+                }
+                lit => return Err(Error::new_spanned(lit, "expected a string")),
+            },
+            other => return Err(Error::new_spanned(other, "unknown domo attribute")),
+        }
+    }
+    Ok(domo_type)
+}
+
+#[derive(Default)]
+struct SerdeField {
+    rename: Option<String>,
+    skip: bool,
+}
+
+fn field_serde_attrs(attrs: &[Attribute]) -> Result<SerdeField, Error> {
+    let mut serde = SerdeField::default();
+    for meta in nested_metas(attrs, "serde")? {
+        match &meta {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                if let Lit::Str(s) = &nv.lit {
+                    serde.rename = Some(s.value());
+                }
+            }
+            // #[serde(rename(serialize = "..."))]
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("rename") => {
+                for nested in &list.nested {
+                    if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                        if nv.path.is_ident("serialize") {
+                            if let Lit::Str(s) = &nv.lit {
+                                serde.rename = Some(s.value());
+                            }
+                        }
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::Path(p))
+                if p.is_ident("skip") || p.is_ident("skip_serializing") =>
+            {
+                serde.skip = true;
+            }
+            NestedMeta::Meta(Meta::Path(p)) if p.is_ident("flatten") => {
+                return Err(Error::new_spanned(
+                    p,
+                    "DomoSchema doesn't support #[serde(flatten)]",
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(serde)
+}
+
+fn container_rename_all(attrs: &[Attribute]) -> Result<Option<String>, Error> {
+    let mut rule = None;
+    for meta in nested_metas(attrs, "serde")? {
+        match &meta {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename_all") => {
+                if let Lit::Str(s) = &nv.lit {
+                    rule = Some(s.value());
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("rename_all") => {
+                for nested in &list.nested {
+                    if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                        if nv.path.is_ident("serialize") {
+                            if let Lit::Str(s) = &nv.lit {
+                                rule = Some(s.value());
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(rule)
+}
+
+/// All `#[name(...)]` items of the given attributes.
+fn nested_metas(attrs: &[Attribute], name: &str) -> Result<Vec<NestedMeta>, Error> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident(name)) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            other => {
+                return Err(Error::new_spanned(
+                    other,
+                    format!("expected #[{}(...)]", name),
+                ))
+            }
+        }
+    }
+    Ok(metas)
+}
+
+/// Apply a serde `rename_all` rule to a snake_case field name.
+fn apply_rename_rule(rule: &str, field: &str) -> String {
+    let words: Vec<&str> = field.split('_').filter(|w| !w.is_empty()).collect();
+    let capitalize = |w: &str| {
+        let mut chars = w.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        }
+    };
+    match rule {
+        "lowercase" => field.to_lowercase(),
+        "UPPERCASE" => field.to_uppercase(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                if i == 0 {
+                    (*w).to_string()
+                } else {
+                    capitalize(w)
+                }
+            })
+            .collect(),
+        "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.replace('_', "-").to_uppercase(),
+        _ => field.to_string(),
+    }
+}
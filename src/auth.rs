@@ -6,12 +6,72 @@ use serde_json;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::io::prelude::*;
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+/// A string holding sensitive data such as a client secret or an access token.
+///
+/// `Debug` and `Display` never print the wrapped value and the memory is zeroed on drop.
+/// Use [`expose_secret`](SecretString::expose_secret) to read the value.
+/// `SecretString` intentionally doesn't implement `Serialize`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new<S: Into<String>>(secret: S) -> Self {
+        SecretString(secret.into())
+    }
+
+    /// Access the secret value.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString(secret.to_owned())
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
 
 /// Domo auth token
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// `access_token` and `jti` are redacted in `Debug` output. To persist a token
+/// (e.g. to a token cache) serialize the view returned by [`DomoToken::expose_secrets`].
+#[derive(Clone, Debug, Deserialize)]
 pub struct DomoToken {
-    pub access_token: String,
+    pub access_token: SecretString,
     pub token_type: String,
     pub expires_in: u32,
     pub scope: String,
@@ -20,10 +80,26 @@ pub struct DomoToken {
     #[serde(rename = "userId")]
     pub user_id: u32,
     pub role: String,
-    pub jti: String,
+    pub jti: SecretString,
     pub domain: String,
 }
 
+/// Serializable view of a [`DomoToken`] with its secrets exposed.
+#[derive(Serialize)]
+pub struct ExposedDomoToken<'a> {
+    pub access_token: &'a str,
+    pub token_type: &'a str,
+    pub expires_in: u32,
+    pub scope: &'a str,
+    pub customer: &'a str,
+    pub env: &'a str,
+    #[serde(rename = "userId")]
+    pub user_id: u32,
+    pub role: &'a str,
+    pub jti: &'a str,
+    pub domain: &'a str,
+}
+
 /// `OAuth` authorization scopes for the Domo API
 #[derive(Default)]
 pub struct DomoScope {
@@ -36,10 +112,41 @@ pub struct DomoScope {
     pub workflow: bool,
 }
 
+/// Type-level marker for a scope that was requested for a token.
+pub struct Granted;
+/// Type-level marker for a scope that was not requested for a token.
+pub struct NotGranted;
+
+/// Type-level set of the `data`, `user`, `audit` and `dashboard` scopes of a token.
+/// Each parameter is either [`Granted`] or [`NotGranted`].
+pub struct Scopes<Data, User, Audit, Dashboard>(PhantomData<(Data, User, Audit, Dashboard)>);
+
+/// Scope marker for clients created from an untyped token.
+/// Scopes aren't checked at compile time so all APIs are available.
+pub struct Unscoped;
+
+/// Implemented by scope sets that allow using the Datasets and Streams APIs.
+pub trait DataScope {}
+/// Implemented by scope sets that allow using the Users and Groups APIs.
+pub trait UserScope {}
+/// Implemented by scope sets that allow using the Activity Log API.
+pub trait AuditScope {}
+/// Implemented by scope sets that allow using the Pages API.
+pub trait DashboardScope {}
+
+impl DataScope for Unscoped {}
+impl UserScope for Unscoped {}
+impl AuditScope for Unscoped {}
+impl DashboardScope for Unscoped {}
+impl<U, A, Db> DataScope for Scopes<Granted, U, A, Db> {}
+impl<D, A, Db> UserScope for Scopes<D, Granted, A, Db> {}
+impl<D, U, Db> AuditScope for Scopes<D, U, Granted, Db> {}
+impl<D, U, A> DashboardScope for Scopes<D, U, A, Granted> {}
+
 /// Object to use to store/retrieve access tokens for Domo API.
 pub struct DomoClientAppCredentials {
     pub client_id: String,
-    pub client_secret: String,
+    pub client_secret: SecretString,
     pub token: Option<DomoToken>,
     pub domo_scope: DomoScope,
 }
@@ -47,13 +154,13 @@ pub struct DomoClientAppCredentials {
 impl DomoToken {
     pub fn default() -> Self {
         Self {
-            access_token: String::new(),
+            access_token: SecretString::default(),
             token_type: String::new(),
             expires_in: 0_u32,
             scope: String::new(),
             customer: String::new(),
             env: String::new(),
-            jti: String::new(),
+            jti: SecretString::default(),
             user_id: 0_u32,
             role: String::new(),
             domain: String::new(),
@@ -61,7 +168,7 @@ impl DomoToken {
     }
 
     pub fn access_token(mut self, access_token: &str) -> Self {
-        self.access_token = access_token.to_string();
+        self.access_token = SecretString::from(access_token);
         self
     }
 
@@ -79,6 +186,32 @@ impl DomoToken {
         self.scope = scope.to_string();
         self
     }
+
+    /// Opt-in to serializing the token including its secrets,
+    /// i.e. when writing the token to a cache.
+    ///
+    /// # Example
+    /// ```
+    /// # use domo_pitchfork::auth::DomoToken;
+    /// let token = DomoToken::default().access_token("abc123");
+    /// let cached = serde_json::to_string(&token.expose_secrets()).unwrap();
+    /// assert!(cached.contains("abc123"));
+    /// assert!(!format!("{:?}", token).contains("abc123"));
+    /// ```
+    pub fn expose_secrets(&self) -> ExposedDomoToken<'_> {
+        ExposedDomoToken {
+            access_token: self.access_token.expose_secret(),
+            token_type: &self.token_type,
+            expires_in: self.expires_in,
+            scope: &self.scope,
+            customer: &self.customer,
+            env: &self.env,
+            user_id: self.user_id,
+            role: &self.role,
+            jti: self.jti.expose_secret(),
+            domain: &self.domain,
+        }
+    }
 }
 
 impl DomoClientAppCredentials {
@@ -86,9 +219,10 @@ impl DomoClientAppCredentials {
         let client_id = env::var("CLIENT_ID")
             // .context("No CLIENT_ID Env Var found")
             .unwrap_or_default();
-        let client_secret = env::var("CLIENT_SECRET")
+        let client_secret: SecretString = env::var("CLIENT_SECRET")
             // .context("No CLIENT_SECRET Env Var found")
-            .unwrap_or_default();
+            .unwrap_or_default()
+            .into();
         let data_scope: bool = env::var("DATA_SCOPE").is_ok();
         let user_scope: bool = env::var("USER_SCOPE").is_ok();
         let audit_scope: bool = env::var("AUDIT_SCOPE").is_ok();
@@ -134,7 +268,7 @@ impl DomoClientAppCredentials {
     }
 
     pub fn client_secret(mut self, client_secret: &str) -> Self {
-        self.client_secret = client_secret.to_string();
+        self.client_secret = SecretString::from(client_secret);
         self
     }
 
@@ -178,6 +312,19 @@ impl DomoClientAppCredentials {
         self
     }
 
+    /// Switch to credentials that track their requested scopes in the type system.
+    /// All scopes are cleared and have to be requested again with the `with_*_scope` methods
+    /// of [`ScopedCredentials`].
+    pub fn scoped(
+        mut self,
+    ) -> ScopedCredentials<Scopes<NotGranted, NotGranted, NotGranted, NotGranted>> {
+        self.domo_scope = DomoScope::default();
+        ScopedCredentials {
+            credentials: self,
+            scopes: PhantomData,
+        }
+    }
+
     pub fn build(self) -> Self {
         const ERROR_MESSAGE: &str = "Set your Domo API Credentials. You can do this by setting environment variables in `.env` file:
         CLIENT_ID='domo-client-id'
@@ -200,12 +347,12 @@ impl DomoClientAppCredentials {
     /// Get cached Domo auth token or authenticate and retrieve a new one
     pub fn get_access_token(&self) -> String {
         match self.token {
-            Some(ref token) => token.access_token.to_owned(),
+            Some(ref token) => token.access_token.expose_secret().to_owned(),
             None => {
                 match self.request_access_token() {
                     Some(new_token) => {
                         //debug!("Token: {:?}", &new_token);
-                        new_token.access_token
+                        new_token.access_token.expose_secret().to_owned()
                     }
                     None => String::new(),
                 }
@@ -242,7 +389,8 @@ impl DomoClientAppCredentials {
             scopes += &"dashboard".to_string();
         }
 
-        if let Some(token) = self.fetch_access_token(&self.client_id, &self.client_secret, &scopes)
+        if let Some(token) =
+            self.fetch_access_token(&self.client_id, self.client_secret.expose_secret(), &scopes)
         {
             Some(token)
         } else {
@@ -260,6 +408,108 @@ impl DomoClientAppCredentials {
     }
 }
 
+/// Credentials that carry their requested scopes as marker types.
+///
+/// A [`DomoPitchfork`](crate::pitchfork::DomoPitchfork) created from the resulting
+/// [`ScopedToken`] only exposes the APIs the token has scope for.
+///
+/// # Example
+/// ```no_run
+/// # use domo_pitchfork::auth::DomoClientAppCredentials;
+/// # use domo_pitchfork::pitchfork::DomoPitchfork;
+/// let auth = DomoClientAppCredentials::default()
+///     .client_id("domo client ID here")
+///     .client_secret("domo secret here")
+///     .scoped()
+///     .with_data_scope();
+/// let token = auth.get_access_token();
+/// let domo = DomoPitchfork::with_scoped_token(&token);
+/// let dataset_list = domo.datasets().list(5, 0);
+/// ```
+///
+/// Calling an API the token doesn't have scope for fails to compile:
+/// ```compile_fail
+/// # use domo_pitchfork::auth::DomoClientAppCredentials;
+/// # use domo_pitchfork::pitchfork::DomoPitchfork;
+/// let auth = DomoClientAppCredentials::default()
+///     .scoped()
+///     .with_data_scope();
+/// let token = auth.get_access_token();
+/// let domo = DomoPitchfork::with_scoped_token(&token);
+/// let log = domo.audit();
+/// ```
+pub struct ScopedCredentials<S> {
+    credentials: DomoClientAppCredentials,
+    scopes: PhantomData<S>,
+}
+
+impl<D, U, A, Db> ScopedCredentials<Scopes<D, U, A, Db>> {
+    pub fn with_data_scope(mut self) -> ScopedCredentials<Scopes<Granted, U, A, Db>> {
+        self.credentials.domo_scope.data = true;
+        ScopedCredentials {
+            credentials: self.credentials,
+            scopes: PhantomData,
+        }
+    }
+
+    pub fn with_user_scope(mut self) -> ScopedCredentials<Scopes<D, Granted, A, Db>> {
+        self.credentials.domo_scope.user = true;
+        ScopedCredentials {
+            credentials: self.credentials,
+            scopes: PhantomData,
+        }
+    }
+
+    pub fn with_audit_scope(mut self) -> ScopedCredentials<Scopes<D, U, Granted, Db>> {
+        self.credentials.domo_scope.audit = true;
+        ScopedCredentials {
+            credentials: self.credentials,
+            scopes: PhantomData,
+        }
+    }
+
+    pub fn with_dashboard_scope(mut self) -> ScopedCredentials<Scopes<D, U, A, Granted>> {
+        self.credentials.domo_scope.dashboard = true;
+        ScopedCredentials {
+            credentials: self.credentials,
+            scopes: PhantomData,
+        }
+    }
+}
+
+impl<S> ScopedCredentials<S> {
+    /// Access the underlying untyped credentials.
+    pub fn credentials(&self) -> &DomoClientAppCredentials {
+        &self.credentials
+    }
+
+    /// Get cached Domo auth token or authenticate and retrieve a new one
+    pub fn get_access_token(&self) -> ScopedToken<S> {
+        ScopedToken {
+            token: self.credentials.get_access_token(),
+            scopes: PhantomData,
+        }
+    }
+}
+
+/// Access token retrieved with [`ScopedCredentials`] that remembers its scopes.
+pub struct ScopedToken<S> {
+    token: String,
+    scopes: PhantomData<S>,
+}
+
+impl<S> ScopedToken<S> {
+    pub fn as_str(&self) -> &str {
+        &self.token
+    }
+}
+
+impl<S> fmt::Debug for ScopedToken<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ScopedToken([REDACTED])")
+    }
+}
+
 fn fetch_access_token(client_id: &str, client_secret: &str, params: &str) -> Option<DomoToken> {
     let client = Client::new();
     let url: Cow<'_, str> = [
@@ -287,3 +537,43 @@ fn fetch_access_token(client_id: &str, client_secret: &str, params: &str) -> Opt
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let creds = DomoClientAppCredentials::default()
+            .client_id("id")
+            .client_secret("super secret")
+            .token_info(DomoToken::default().access_token("access token value"));
+        let token = creds.token.as_ref().unwrap();
+        assert_eq!(format!("{}", creds.client_secret), "[REDACTED]");
+        assert!(!format!("{:?}", creds.client_secret).contains("super secret"));
+        assert!(!format!("{:?}", token).contains("access token value"));
+        assert_eq!(creds.get_access_token(), "access token value");
+    }
+
+    #[test]
+    fn test_token_deserializes_secrets() {
+        let json = r#"{
+            "access_token": "abc",
+            "token_type": "bearer",
+            "expires_in": 3599,
+            "scope": "data",
+            "customer": "acme",
+            "env": "prod1",
+            "userId": 1,
+            "role": "Admin",
+            "jti": "xyz",
+            "domain": "acme.domo.com"
+        }"#;
+        let token: DomoToken = serde_json::from_str(json).unwrap();
+        assert_eq!(token.access_token.expose_secret(), "abc");
+        assert_eq!(token.jti.expose_secret(), "xyz");
+        let v = serde_json::to_value(token.expose_secrets()).unwrap();
+        assert_eq!(v["access_token"], "abc");
+        assert_eq!(v["userId"], 1);
+    }
+}
@@ -5,22 +5,29 @@
 //! Additional Resources:
 //! - [Domo Dataset API Reference](https://developer.domo.com/docs/dataset-api-reference/dataset)
 use super::policy::Policy;
+use super::stream::UpdateMethod;
 use super::user::Owner;
 use crate::util::csv::{deserialize_csv_str, serialize_to_csv_str};
+use crate::util::progress::{csv_rows, ProgressReader, ProgressReporter, ProgressTracker};
+use crate::util::sql::{bind_params, SqlParam};
+use crate::util::validate::{validate_csv, ValidateOptions};
 use chrono::FixedOffset;
 use serde_json::json;
 use serde_json::Value;
 
 use crate::error::{PitchforkError, PitchforkErrorKind};
-use crate::pitchfork::{DatasetsRequestBuilder, DomoRequest};
-use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use crate::pitchfork::{DatasetsRequestBuilder, DomoPitchfork, DomoRequest, DomoRequestBuilder};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use log::debug;
-use reqwest::Method;
+use reqwest::{Body, Method};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
+use std::io::{Cursor, Read};
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 impl<'t> DatasetsRequestBuilder<'t, Dataset> {
     /// Retreives details for a `Dataset`
@@ -58,10 +65,32 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
     /// dataset_list.iter().map(|ds| println!("Dataset Name: {}", ds.name.as_ref().unwrap()));
     /// # Ok::<(),PitchforkError>(())
     /// ```
-    pub fn list(mut self, limit: u32, offset: u32) -> Result<Vec<Dataset>, PitchforkError> {
-        // TODO: impl sort optional query param
-        self.url
-            .push_str(&format!("?limit={}&offset={}", limit, offset));
+    pub fn list(self, limit: u32, offset: u32) -> Result<Vec<Dataset>, PitchforkError> {
+        let query = DatasetListQuery::new().limit(limit).offset(offset);
+        self.list_with_query(&query)
+    }
+
+    /// List Datasets matching a [`DatasetListQuery`].
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::dataset::{DatasetListQuery, DatasetSortField, SortDirection};
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let query = DatasetListQuery::new()
+    ///     .name_like("Sales")
+    ///     .sort(DatasetSortField::LastUpdated, SortDirection::Descending)
+    ///     .limit(10);
+    /// let dataset_list = domo.datasets().list_with_query(&query)?;
+    /// dataset_list.iter().map(|ds| println!("Dataset Name: {}", ds.name.as_ref().unwrap()));
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn list_with_query(
+        mut self,
+        query: &DatasetListQuery,
+    ) -> Result<Vec<Dataset>, PitchforkError> {
+        self.url.push('?');
+        self.url.push_str(&query.create_query_string());
         let req = Self {
             method: Method::GET,
             auth: self.auth,
@@ -73,6 +102,68 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         Ok(ds_list)
     }
 
+    /// Page through all Datasets matching a [`DatasetListQuery`], starting at the query's offset.
+    /// Each item of the returned iterator is one page of up to `limit` datasets.
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::dataset::DatasetListQuery;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let query = DatasetListQuery::new().name_like("Sales").fields(&["id", "name"]);
+    /// for page in domo.datasets().paginate(query) {
+    ///     for ds in page? {
+    ///         println!("Dataset Name: {}", ds.name.as_ref().unwrap());
+    ///     }
+    /// }
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn paginate(self, query: DatasetListQuery) -> DatasetPages<'t> {
+        DatasetPages {
+            auth: self.auth,
+            url: self.url,
+            query,
+            done: false,
+        }
+    }
+
+    /// Find the Dataset with exactly the given name.
+    /// Returns `Ok(None)` if no Dataset has that name and a
+    /// [`PitchforkErrorKind::AmbiguousName`] error listing the matching dataset ids
+    /// if more than one does.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// if let Some(ds) = domo.datasets().find_by_name("Sales Pipeline")? {
+    ///     println!("Dataset Id: {}", ds.id);
+    /// }
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn find_by_name(self, name: &str) -> Result<Option<Dataset>, PitchforkError> {
+        let query = DatasetListQuery::new().name_like(name);
+        let mut matches = Vec::new();
+        for page in self.paginate(query) {
+            matches.extend(
+                page?
+                    .into_iter()
+                    .filter(|ds| ds.name.as_ref().map(String::as_str) == Some(name)),
+            );
+        }
+        single_name_match(name, matches, |ds| ds.id.clone())
+    }
+
+    /// Find the Dataset named `ds_meta.name` or create it from `ds_meta` if it doesn't exist.
+    pub fn find_or_create(self, ds_meta: &DatasetSchema) -> Result<Dataset, PitchforkError> {
+        let domo = DomoPitchfork::with_token(self.auth);
+        match self.find_by_name(&ds_meta.name)? {
+            Some(ds) => Ok(ds),
+            None => domo.datasets().create(ds_meta),
+        }
+    }
+
     /// Create a new empty Domo Dataset.
     pub fn create(self, ds_meta: &DatasetSchema) -> Result<Dataset, PitchforkError> {
         let body = serde_json::to_string(ds_meta)?;
@@ -133,6 +224,65 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         Ok(ds)
     }
 
+    /// Compare a Dataset's current schema with `local`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::dataset::Schema;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// # let local = Schema { columns: vec![] };
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let diff = domo.datasets().schema_diff("dataset id", &local)?;
+    /// if !diff.is_empty() {
+    ///     println!("Schema changes: {}", diff);
+    /// }
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn schema_diff(
+        self,
+        dataset_id: &str,
+        local: &Schema,
+    ) -> Result<SchemaDiff, PitchforkError> {
+        let ds = self.info(dataset_id)?;
+        Ok(ds.schema.unwrap_or_default().diff(local))
+    }
+
+    /// Update a Dataset's schema to `local`, returning the updated Dataset.
+    /// This also works for the Dataset of a Stream.
+    ///
+    /// With `SchemaMigration::CompatibleOnly` nothing is changed and a
+    /// `PitchforkErrorKind::DestructiveSchemaChange` error is returned if columns would be
+    /// removed, retyped, reordered or added anywhere but the end. The Dataset isn't modified if the schemas match.
+    pub fn apply_schema(
+        self,
+        dataset_id: &str,
+        local: &Schema,
+        migration: SchemaMigration,
+    ) -> Result<Dataset, PitchforkError> {
+        let domo = DomoPitchfork::with_token(self.auth);
+        let ds = self.info(dataset_id)?;
+        let diff = ds.schema.clone().unwrap_or_default().diff(local);
+        if diff.is_empty() {
+            return Ok(ds);
+        }
+        if diff.is_destructive() && migration == SchemaMigration::CompatibleOnly {
+            let kind =
+                PitchforkErrorKind::DestructiveSchemaChange(dataset_id.to_string(), Box::new(diff));
+            return Err(kind.into());
+        }
+        let ds_meta = DatasetSchema {
+            name: ds.name.unwrap_or_default(),
+            description: ds.description.unwrap_or_default(),
+            rows: ds
+                .rows
+                .and_then(|rows| u32::try_from(rows).ok())
+                .unwrap_or(0),
+            schema: local.clone(),
+        };
+        domo.datasets().modify(dataset_id, &ds_meta)
+    }
+
     /// Returns data from the DataSet based on a SQL query.
     /// # Example
     /// ```no_run
@@ -167,6 +317,62 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         Ok(dq)
     }
 
+    /// Returns data from the DataSet based on a SQL query with `?` placeholders.
+    /// Params are escaped and substituted for the placeholders in order, see
+    /// [`bind_params`](crate::util::sql::bind_params).
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let region = "Pacific Northwest"; // user supplied filter value
+    /// let dq = domo.datasets().query_data_with_params(
+    ///     "ds_id",
+    ///     "SELECT * FROM table WHERE `Region` = ? AND `Year` IN ?",
+    ///     &[region.into(), vec![2018, 2019].into()],
+    /// )?;
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn query_data_with_params(
+        self,
+        dataset_id: &str,
+        sql_query: &str,
+        params: &[SqlParam],
+    ) -> Result<DatasetQueryData, PitchforkError> {
+        let sql = bind_params(sql_query, params)?;
+        debug!("sql: {}", sql);
+        self.query_data(dataset_id, &sql)
+    }
+
+    /// Returns data from the DataSet based on a SQL query deserialized into a Vec<T>.
+    /// See [`DatasetQueryData::deserialize_rows`] for how column types are converted.
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use chrono::NaiveDate;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Order {
+    ///     #[serde(rename = "Order Date")]
+    ///     order_date: NaiveDate,
+    ///     #[serde(rename = "Quantity")]
+    ///     quantity: i64,
+    /// }
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let orders: Vec<Order> = domo.datasets()
+    ///     .query_as("ds_id", "SELECT `Order Date`, `Quantity` FROM table")?;
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn query_as<T: DeserializeOwned>(
+        self,
+        dataset_id: &str,
+        sql_query: &str,
+    ) -> Result<Vec<T>, PitchforkError> {
+        self.query_data(dataset_id, sql_query)?.deserialize_rows()
+    }
+
     /// Retrieve data from a Domo Dataset as a csv string.
     pub fn download_data(
         mut self,
@@ -180,6 +386,50 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         self.send_json()?.text().map_err(PitchforkError::from)
     }
 
+    /// Retrieve data from a Domo Dataset as a csv string, reporting the bytes received
+    /// and, once the download is done, the number of rows.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use domo_pitchfork::util::progress::LogProgress;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let progress = Arc::new(LogProgress::new("orders download", Duration::from_secs(30)));
+    /// let csv = domo.datasets().download_data_with_progress("ds_id", true, progress)?;
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn download_data_with_progress(
+        mut self,
+        dataset_id: &str,
+        include_csv_headers: bool,
+        progress: Arc<dyn ProgressReporter>,
+    ) -> Result<String, PitchforkError> {
+        self.url.push_str(&format!(
+            "{}/data?includeHeader={}",
+            dataset_id, include_csv_headers
+        ));
+        let res = self.send_json()?;
+        let tracker = Arc::new(ProgressTracker::new(
+            progress,
+            res.content_length(),
+            Some(1),
+        ));
+        let mut csv = String::new();
+        ProgressReader::new(res, Arc::clone(&tracker)).read_to_string(&mut csv)?;
+        let rows = csv_rows(csv.as_bytes());
+        let header_rows = if include_csv_headers && rows > 0 {
+            1
+        } else {
+            0
+        };
+        tracker.part_done(rows - header_rows);
+        tracker.finish();
+        Ok(csv)
+    }
+
     /// Retrieve data from a Domo Dataset and Deserialize the retrieved data into a Vec<T>.
     pub fn get_data<T: DeserializeOwned>(
         mut self,
@@ -190,13 +440,29 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         deserialize_csv_str(&self.send_json()?.text().map_err(PitchforkError::from)?)
     }
 
-    /// Upload data to the Domo Dataset.
+    /// Upload data to the Domo Dataset, replacing or appending to the existing data.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::UpdateMethod;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// domo.datasets()
+    ///     .upload_from_str("ds_id", "Test,1\n".to_string(), &UpdateMethod::Append)?;
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
     pub fn upload_from_str(
         mut self,
         dataset_id: &str,
         data_rows: String,
+        update_method: &UpdateMethod,
     ) -> Result<(), PitchforkError> {
-        self.url.push_str(&format!("{}/data", dataset_id));
+        self.url.push_str(&format!(
+            "{}/data?updateMethod={}",
+            dataset_id,
+            update_method.as_str()
+        ));
         let req = Self {
             method: Method::PUT,
             auth: self.auth,
@@ -208,16 +474,52 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         Ok(())
     }
 
-    /// Upload data to the Domo Dataset.
+    /// Upload data to the Domo Dataset like [`upload_from_str`](Self::upload_from_str),
+    /// reporting the bytes sent as the data streams to Domo.
+    pub fn upload_from_str_with_progress(
+        mut self,
+        dataset_id: &str,
+        data_rows: String,
+        update_method: &UpdateMethod,
+        progress: Arc<dyn ProgressReporter>,
+    ) -> Result<(), PitchforkError> {
+        self.url.push_str(&format!(
+            "{}/data?updateMethod={}",
+            dataset_id,
+            update_method.as_str()
+        ));
+        let req = Self {
+            method: Method::PUT,
+            auth: self.auth,
+            url: self.url,
+            resp_t: PhantomData,
+            body: None,
+        };
+        let len = data_rows.len() as u64;
+        let rows = csv_rows(data_rows.as_bytes());
+        let tracker = Arc::new(ProgressTracker::new(progress, Some(len), Some(1)));
+        let reader = ProgressReader::new(Cursor::new(data_rows.into_bytes()), Arc::clone(&tracker));
+        req.send_csv_body(Body::sized(reader, len))?;
+        tracker.part_done(rows);
+        tracker.finish();
+        Ok(())
+    }
+
+    /// Upload data to the Domo Dataset, replacing or appending to the existing data.
     pub fn upload_serializable<T: Serialize>(
         mut self,
         dataset_id: &str,
         data: &[T],
+        update_method: &UpdateMethod,
     ) -> Result<(), PitchforkError> {
         if data.is_empty() {
             return Err(PitchforkError::new("data is empty"));
         }
-        self.url.push_str(&format!("{}/data", dataset_id));
+        self.url.push_str(&format!(
+            "{}/data?updateMethod={}",
+            dataset_id,
+            update_method.as_str()
+        ));
         let req = Self {
             method: Method::PUT,
             auth: self.auth,
@@ -232,6 +534,51 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
         Ok(())
     }
 
+    /// Validate csv data against `schema` and upload it to the Domo Dataset if it's valid.
+    /// Returns a `PitchforkErrorKind::Validation` error with the issues found otherwise.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::UpdateMethod;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let schema = domo.datasets().info("ds_id")?.schema.unwrap_or_default();
+    /// domo.datasets().upload_from_str_checked(
+    ///     "ds_id",
+    ///     "1,2019-07-10\n".to_string(),
+    ///     &UpdateMethod::Replace,
+    ///     &schema,
+    /// )?;
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn upload_from_str_checked(
+        self,
+        dataset_id: &str,
+        data_rows: String,
+        update_method: &UpdateMethod,
+        schema: &Schema,
+    ) -> Result<(), PitchforkError> {
+        validate_csv(&data_rows, schema, &ValidateOptions::new()).into_result()?;
+        self.upload_from_str(dataset_id, data_rows, update_method)
+    }
+
+    /// Validate records against `schema` and upload them to the Domo Dataset if they're valid.
+    /// Returns a `PitchforkErrorKind::Validation` error with the issues found otherwise.
+    pub fn upload_serializable_checked<T: Serialize>(
+        self,
+        dataset_id: &str,
+        data: &[T],
+        update_method: &UpdateMethod,
+        schema: &Schema,
+    ) -> Result<(), PitchforkError> {
+        if data.is_empty() {
+            return Err(PitchforkError::new("data is empty"));
+        }
+        let csv = serialize_to_csv_str(&data, false)?;
+        self.upload_from_str_checked(dataset_id, csv, update_method, schema)
+    }
+
     /// Retrieves details of a given policy for a Dataset
     pub fn pdp_policy_info(
         mut self,
@@ -327,6 +674,171 @@ impl<'t> DatasetsRequestBuilder<'t, Dataset> {
     }
 }
 
+/// Returns the only candidate matching `name`, `None` if there are no candidates
+/// or an `AmbiguousName` error with the ids of all candidates.
+pub(crate) fn single_name_match<T>(
+    name: &str,
+    mut candidates: Vec<T>,
+    id: impl Fn(&T) -> String,
+) -> Result<Option<T>, PitchforkError> {
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(candidates.pop()),
+        _ => Err(PitchforkErrorKind::AmbiguousName(
+            name.to_string(),
+            candidates.iter().map(id).collect(),
+        )
+        .into()),
+    }
+}
+
+/// Dataset fields a dataset list can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DatasetSortField {
+    Name,
+    LastTouched,
+    LastUpdated,
+    CardCount,
+    CardViewCount,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+/// Query params for listing Datasets.
+/// [Domo Dataset API List Reference](https://developer.domo.com/docs/dataset-api-reference/dataset#List%20DataSets)
+#[derive(Clone, Debug)]
+pub struct DatasetListQuery {
+    /// The amount of datasets to return in the list. Default is 50, maximum is 50.
+    pub limit: u32,
+    /// The offset of the dataset to begin the list within the response.
+    pub offset: u32,
+    pub sort: Option<(DatasetSortField, SortDirection)>,
+    /// Limit the response to datasets with names containing this value.
+    pub name_like: Option<String>,
+    pub owner_id: Option<u64>,
+    /// Dataset fields to include in the response. All fields are returned if empty.
+    pub fields: Vec<String>,
+}
+
+impl Default for DatasetListQuery {
+    fn default() -> Self {
+        Self {
+            limit: 50,
+            offset: 0,
+            sort: None,
+            name_like: None,
+            owner_id: None,
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl DatasetListQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn sort(mut self, field: DatasetSortField, direction: SortDirection) -> Self {
+        self.sort = Some((field, direction));
+        self
+    }
+
+    pub fn name_like(mut self, name: &str) -> Self {
+        self.name_like = Some(name.to_string());
+        self
+    }
+
+    pub fn owner_id(mut self, owner_id: u64) -> Self {
+        self.owner_id = Some(owner_id);
+        self
+    }
+
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.fields = fields.iter().map(|f| (*f).to_string()).collect();
+        self
+    }
+
+    pub(crate) fn create_query_string(&self) -> String {
+        let mut q = url::form_urlencoded::Serializer::new(String::new());
+        q.append_pair("limit", &self.limit.to_string());
+        q.append_pair("offset", &self.offset.to_string());
+        if let Some((field, direction)) = self.sort {
+            let field = match field {
+                DatasetSortField::Name => "name",
+                DatasetSortField::LastTouched => "lastTouched",
+                DatasetSortField::LastUpdated => "lastUpdated",
+                DatasetSortField::CardCount => "cardCount",
+                DatasetSortField::CardViewCount => "cardViewCount",
+            };
+            // Domo reverses the sort for fields prefixed with a '-'
+            let sort = match direction {
+                SortDirection::Ascending => field.to_string(),
+                SortDirection::Descending => format!("-{}", field),
+            };
+            q.append_pair("sort", &sort);
+        }
+        if let Some(name) = &self.name_like {
+            q.append_pair("nameLike", name);
+        }
+        if let Some(owner_id) = self.owner_id {
+            q.append_pair("ownerId", &owner_id.to_string());
+        }
+        if !self.fields.is_empty() {
+            q.append_pair("fields", &self.fields.join(","));
+        }
+        q.finish()
+    }
+}
+
+/// Iterator over pages of Datasets returned by
+/// [`DatasetsRequestBuilder::paginate`](crate::pitchfork::DatasetsRequestBuilder::paginate).
+pub struct DatasetPages<'t> {
+    auth: &'t str,
+    url: String,
+    query: DatasetListQuery,
+    done: bool,
+}
+
+impl<'t> Iterator for DatasetPages<'t> {
+    type Item = Result<Vec<Dataset>, PitchforkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let req: DatasetsRequestBuilder<'t, Dataset> =
+            DomoRequestBuilder::new(self.auth, self.url.clone()).into();
+        let page = req.list_with_query(&self.query);
+        match &page {
+            Ok(datasets) => {
+                if datasets.len() < self.query.limit as usize || self.query.limit == 0 {
+                    self.done = true;
+                }
+                if datasets.is_empty() {
+                    return None;
+                }
+                self.query.offset += self.query.limit;
+            }
+            Err(_) => self.done = true,
+        }
+        Some(page)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DatasetQueryData {
     pub datasource: String,
@@ -341,6 +853,97 @@ pub struct DatasetQueryData {
     pub from_cache: bool,
 }
 
+impl DatasetQueryData {
+    /// Deserialize each row into a `T` by pairing `columns` with the row's values,
+    /// so struct fields are matched by column name.
+    ///
+    /// Values are converted based on the column's `metadata.data_type` first:
+    /// - `LONG` columns become integers and `DECIMAL`/`DOUBLE` columns become floats.
+    /// - `DATE` columns become `YYYY-MM-DD` strings that deserialize into `chrono::NaiveDate`.
+    /// - `DATETIME` columns become RFC 3339 UTC timestamps that deserialize into `chrono::DateTime<Utc>`.
+    pub fn deserialize_rows<T: DeserializeOwned>(&self) -> Result<Vec<T>, PitchforkError> {
+        self.rows
+            .iter()
+            .map(|row| {
+                if row.len() != self.columns.len() {
+                    return Err(PitchforkError::new(format!(
+                        "query row has {} values but there are {} columns",
+                        row.len(),
+                        self.columns.len()
+                    )));
+                }
+                let mut record = serde_json::Map::with_capacity(row.len());
+                for (i, (col, value)) in self.columns.iter().zip(row).enumerate() {
+                    let value = match self.metadata.get(i) {
+                        Some(meta) => convert_query_value(&meta.domo_type(), value),
+                        None => value.clone(),
+                    };
+                    record.insert(col.clone(), value);
+                }
+                serde_json::from_value(Value::Object(record)).map_err(PitchforkError::from)
+            })
+            .collect()
+    }
+}
+
+/// Convert a query result value into the json representation matching its Domo data type.
+/// Values that can't be converted are passed through unchanged.
+#[allow(clippy::cast_possible_truncation)]
+fn convert_query_value(data_type: &DomoDataType, value: &Value) -> Value {
+    match (data_type, value) {
+        (_, Value::Null) => Value::Null,
+        (DomoDataType::LONG, Value::Number(n)) if n.is_f64() => n
+            .as_f64()
+            .filter(|f| f.fract() == 0.0)
+            .map_or_else(|| value.clone(), |f| json!(f as i64)),
+        (DomoDataType::LONG, Value::String(s)) => s
+            .trim()
+            .parse::<i64>()
+            .map_or_else(|_| value.clone(), |i| json!(i)),
+        (DomoDataType::DECIMAL | DomoDataType::DOUBLE, Value::Number(n)) => {
+            n.as_f64().map_or_else(|| value.clone(), |f| json!(f))
+        }
+        (DomoDataType::DECIMAL | DomoDataType::DOUBLE, Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .map_or_else(|_| value.clone(), |f| json!(f)),
+        (DomoDataType::DATE, Value::String(s)) => parse_domo_datetime(s)
+            .map(|dt| dt.naive_utc().date())
+            .or_else(|| s.parse::<NaiveDate>().ok())
+            .map_or_else(
+                || value.clone(),
+                |d| json!(d.format("%Y-%m-%d").to_string()),
+            ),
+        (DomoDataType::DATE, Value::Number(n)) => n
+            .as_i64()
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+            .map_or_else(
+                || value.clone(),
+                |dt| json!(dt.format("%Y-%m-%d").to_string()),
+            ),
+        (DomoDataType::DATETIME, Value::String(s)) => {
+            parse_domo_datetime(s).map_or_else(|| value.clone(), |dt| json!(dt.to_rfc3339()))
+        }
+        (DomoDataType::DATETIME, Value::Number(n)) => n
+            .as_i64()
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+            .map_or_else(|| value.clone(), |dt| json!(dt.to_rfc3339())),
+        _ => value.clone(),
+    }
+}
+
+/// Parse the datetime formats Domo returns. Timestamps without an offset are assumed to be UTC.
+fn parse_domo_datetime(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = s.parse::<DateTime<FixedOffset>>() {
+        return Some(dt.with_timezone(&Utc));
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .filter_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .map(|ndt| Utc.from_utc_datetime(&ndt))
+        .next()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DataQueryMetadata {
     #[serde(rename = "type")]
@@ -354,6 +957,13 @@ pub struct DataQueryMetadata {
     #[serde(rename = "periodIndex")]
     pub period_index: i32,
 }
+
+impl DataQueryMetadata {
+    /// The column's `data_type` as a [`DomoDataType`].
+    pub fn domo_type(&self) -> DomoDataType {
+        DomoDataType::from(self.data_type.as_str())
+    }
+}
 ///[Dataset object](https://developer.domo.com/docs/dataset-api-reference/dataset#The%20DataSet%20object)
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Dataset {
@@ -385,7 +995,7 @@ pub struct DatasetSchema {
 
 // TODO: Fix Link
 ///[Schema Object](https://developer.domo.com/)
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Schema {
     #[serde(rename = "columns")]
     pub columns: Vec<Column>,
@@ -394,11 +1004,54 @@ pub struct Schema {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Column {
     #[serde(rename = "type")]
-    pub column_type: String,
+    pub column_type: DomoDataType,
     pub name: String,
 }
 
+/// Types that map onto a Domo [`Schema`] with one column per field.
+///
+/// With the `derive` feature enabled this can be derived for structs with
+/// `#[derive(DomoSchema)]`, keeping the schema in sync with the records passed to
+/// `upload_serializable` and `upload_serializable_part`.
+///
+/// # Example
+/// Requires the `derive` feature.
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// use domo_pitchfork::domo::dataset::{DatasetSchema, DomoSchema};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize, DomoSchema)]
+/// struct Order {
+///     #[serde(rename = "Order Id")]
+///     id: i64,
+///     #[serde(rename = "Order Date")]
+///     date: chrono::NaiveDate,
+///     amount: f64,
+///     #[serde(skip)]
+///     internal_note: String,
+/// }
+/// let ds_schema = DatasetSchema::from_domo_schema::<Order>("Orders", "All shop orders");
+/// ```
+pub trait DomoSchema {
+    fn domo_schema() -> Schema;
+}
+
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub use domo_pitchfork_derive::DomoSchema;
+
 impl DatasetSchema {
+    /// Create a `DatasetSchema` with the columns of a [`DomoSchema`] type.
+    pub fn from_domo_schema<T: DomoSchema>(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            rows: 0,
+            schema: T::domo_schema(),
+        }
+    }
+
     pub fn from_hashmap(
         name: String,
         description: String,
@@ -414,19 +1067,150 @@ impl DatasetSchema {
 }
 
 impl Schema {
+    /// Create a Schema from a map of column names to types.
+    /// Column order isn't preserved, use [`Schema::from_field_types`] or
+    /// [`infer_schema`](crate::util::schema::infer_schema) if order matters.
     pub fn from_hashmap(cols: &HashMap<String, FieldType>) -> Self {
         let mut columns: Vec<Column> = Vec::new();
         for (col, typ) in cols {
-            let typ_str = DomoDataType::from_fieldtype(*typ).to_string();
             columns.push(Column {
-                column_type: typ_str,
+                column_type: DomoDataType::from_fieldtype(*typ),
                 name: col.to_string(),
             })
         }
         Self { columns }
     }
+
+    /// Create a Schema from column names and types, keeping their order.
+    pub fn from_field_types(cols: &[(String, FieldType)]) -> Self {
+        let columns = cols
+            .iter()
+            .map(|(col, typ)| Column {
+                column_type: DomoDataType::from_fieldtype(*typ),
+                name: col.to_string(),
+            })
+            .collect();
+        Self { columns }
+    }
+
+    /// Compare this (remote) schema against a `local` schema.
+    /// The returned diff lists the changes needed to turn `self` into `local`.
+    pub fn diff(&self, local: &Schema) -> SchemaDiff {
+        let find = |cols: &[Column], name: &str| cols.iter().position(|c| c.name == name);
+        let added: Vec<Column> = local
+            .columns
+            .iter()
+            .filter(|c| find(&self.columns, &c.name).is_none())
+            .cloned()
+            .collect();
+        let removed: Vec<Column> = self
+            .columns
+            .iter()
+            .filter(|c| find(&local.columns, &c.name).is_none())
+            .cloned()
+            .collect();
+        let mut retyped = Vec::new();
+        let mut local_positions = Vec::new();
+        for col in &self.columns {
+            if let Some(j) = find(&local.columns, &col.name) {
+                let local_col = &local.columns[j];
+                if local_col.column_type != col.column_type {
+                    retyped.push(ColumnTypeChange {
+                        name: col.name.clone(),
+                        remote: col.column_type.clone(),
+                        local: local_col.column_type.clone(),
+                    });
+                }
+                local_positions.push(j);
+            }
+        }
+        // since data is positional, the shared columns must lead the local schema in remote
+        // order, so a column added anywhere but the end moves the columns after it
+        let reordered = local_positions.iter().enumerate().any(|(k, &j)| k != j);
+        SchemaDiff {
+            added,
+            removed,
+            retyped,
+            reordered,
+        }
+    }
+}
+
+/// A column whose type differs between the remote and local schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnTypeChange {
+    pub name: String,
+    pub remote: DomoDataType,
+    pub local: DomoDataType,
+}
+
+/// Differences between a remote Dataset schema and a local one, see [`Schema::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Columns only in the local schema.
+    pub added: Vec<Column>,
+    /// Columns only in the remote schema.
+    pub removed: Vec<Column>,
+    /// Columns in both schemas with a different type.
+    pub retyped: Vec<ColumnTypeChange>,
+    /// Whether the columns in both schemas are in a different order or at different
+    /// positions, as when a column is added before them instead of appended.
+    pub reordered: bool,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retyped.is_empty()
+            && !self.reordered
+    }
+
+    /// Removed and retyped columns can lose existing data when applied, and since Domo
+    /// data is positional, reordered columns misalign the existing rows.
+    pub fn is_destructive(&self) -> bool {
+        !self.removed.is_empty() || !self.retyped.is_empty() || self.reordered
+    }
 }
 
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no schema changes");
+        }
+        let mut changes = Vec::new();
+        for c in &self.added {
+            changes.push(format!("added {} ({})", c.name, c.column_type));
+        }
+        for c in &self.removed {
+            changes.push(format!("removed {} ({})", c.name, c.column_type));
+        }
+        for c in &self.retyped {
+            changes.push(format!("retyped {} ({} -> {})", c.name, c.remote, c.local));
+        }
+        if self.reordered {
+            changes.push("reordered columns".to_string());
+        }
+        write!(f, "{}", changes.join(", "))
+    }
+}
+
+/// Which schema changes [`apply_schema`](crate::pitchfork::DatasetsRequestBuilder::apply_schema) may make.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchemaMigration {
+    /// Only append columns. Fails if columns would be removed, retyped, reordered or
+    /// inserted before existing columns.
+    CompatibleOnly,
+    /// Apply all changes, including removing, retyping and reordering columns.
+    AllowDestructive,
+}
+
+/// Domo column data type.
+///
+/// Serializes to and from the type names Domo uses (`"STRING"`, `"LONG"`, ...).
+/// Types this crate doesn't know about deserialize into `Unknown` so newer
+/// server types don't break deserializing a `Schema`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum DomoDataType {
     STRING,
     LONG,
@@ -434,6 +1218,7 @@ pub enum DomoDataType {
     DOUBLE,
     DATETIME,
     DATE,
+    Unknown(String),
 }
 
 impl DomoDataType {
@@ -446,31 +1231,97 @@ impl DomoDataType {
             FieldType::TDate => DomoDataType::DATE,
         }
     }
+
+    /// The closest csv `FieldType` for this Domo type. `Unknown` types map to `TUnknown`.
+    pub fn to_field_type(&self) -> FieldType {
+        match self {
+            DomoDataType::STRING => FieldType::TUnicode,
+            DomoDataType::LONG => FieldType::TInteger,
+            DomoDataType::DECIMAL | DomoDataType::DOUBLE => FieldType::TFloat,
+            DomoDataType::DATETIME => FieldType::TDateTime,
+            DomoDataType::DATE => FieldType::TDate,
+            DomoDataType::Unknown(_) => FieldType::TUnknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            DomoDataType::STRING => "STRING",
+            DomoDataType::LONG => "LONG",
+            DomoDataType::DECIMAL => "DECIMAL",
+            DomoDataType::DOUBLE => "DOUBLE",
+            DomoDataType::DATETIME => "DATETIME",
+            DomoDataType::DATE => "DATE",
+            DomoDataType::Unknown(typ) => typ,
+        }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, DomoDataType::Unknown(_))
+    }
+}
+
+impl From<FieldType> for DomoDataType {
+    fn from(typ: FieldType) -> Self {
+        DomoDataType::from_fieldtype(typ)
+    }
+}
+
+impl From<&str> for DomoDataType {
+    fn from(typ: &str) -> Self {
+        match typ {
+            "STRING" => DomoDataType::STRING,
+            "LONG" => DomoDataType::LONG,
+            "DECIMAL" => DomoDataType::DECIMAL,
+            "DOUBLE" => DomoDataType::DOUBLE,
+            "DATETIME" => DomoDataType::DATETIME,
+            "DATE" => DomoDataType::DATE,
+            other => DomoDataType::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for DomoDataType {
+    fn from(typ: String) -> Self {
+        match DomoDataType::from(typ.as_str()) {
+            DomoDataType::Unknown(_) => DomoDataType::Unknown(typ),
+            known => known,
+        }
+    }
+}
+
+impl std::str::FromStr for DomoDataType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(DomoDataType::from(s))
+    }
 }
 
 impl From<DomoDataType> for String {
     fn from(domo_type: DomoDataType) -> Self {
         match domo_type {
-            DomoDataType::STRING => "STRING".to_owned(),
-            DomoDataType::LONG => "LONG".to_owned(),
-            DomoDataType::DECIMAL => "DECIMAL".to_owned(),
-            DomoDataType::DOUBLE => "DOUBLE".to_owned(),
-            DomoDataType::DATETIME => "DATETIME".to_owned(),
-            DomoDataType::DATE => "DATE".to_owned(),
+            DomoDataType::Unknown(typ) => typ,
+            known => known.as_str().to_owned(),
         }
     }
 }
 
 impl fmt::Display for DomoDataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DomoDataType::STRING => write!(f, "STRING"),
-            DomoDataType::LONG => write!(f, "LONG"),
-            DomoDataType::DECIMAL => write!(f, "DECIMAL"),
-            DomoDataType::DOUBLE => write!(f, "DOUBLE"),
-            DomoDataType::DATETIME => write!(f, "DATETIME"),
-            DomoDataType::DATE => write!(f, "DATE"),
-        }
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for DomoDataType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for DomoDataType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(DomoDataType::from)
     }
 }
 
@@ -607,7 +1458,7 @@ mod tests {
     #[test]
     fn test_dataset_schema_serialization() {
         let c = Column {
-            column_type: "STRING".to_string(),
+            column_type: DomoDataType::STRING,
             name: "column name".to_string(),
         };
         let s = Schema { columns: vec![c] };
@@ -633,6 +1484,198 @@ mod tests {
         assert_eq!(v, expected);
     }
 
+    #[test]
+    fn test_domo_data_type_serde() {
+        let cols: Vec<Column> = serde_json::from_value(json!([
+            {"type": "LONG", "name": "a"},
+            {"type": "GEOPOINT", "name": "b"}
+        ]))
+        .unwrap();
+        assert_eq!(cols[0].column_type, DomoDataType::LONG);
+        assert_eq!(
+            cols[1].column_type,
+            DomoDataType::Unknown("GEOPOINT".to_string())
+        );
+        assert_eq!(cols[1].column_type.to_field_type(), FieldType::TUnknown);
+        let v = serde_json::to_value(&cols).unwrap();
+        assert_eq!(v[1]["type"], "GEOPOINT");
+        assert_eq!(
+            DomoDataType::from(FieldType::TFloat).to_field_type(),
+            FieldType::TFloat
+        );
+    }
+
+    #[test]
+    fn test_dataset_list_query_string() {
+        let q = DatasetListQuery::new()
+            .limit(10)
+            .offset(20)
+            .sort(DatasetSortField::CardViewCount, SortDirection::Descending)
+            .name_like("Sales & Ops")
+            .owner_id(123)
+            .fields(&["id", "name"]);
+        assert_eq!(
+            q.create_query_string(),
+            "limit=10&offset=20&sort=-cardViewCount&nameLike=Sales+%26+Ops&ownerId=123&fields=id%2Cname"
+        );
+        assert_eq!(
+            DatasetListQuery::new().create_query_string(),
+            "limit=50&offset=0"
+        );
+    }
+
+    #[test]
+    fn test_single_name_match() {
+        let none: Vec<u64> = vec![];
+        assert_eq!(single_name_match("a", none, u64::to_string).unwrap(), None);
+        assert_eq!(
+            single_name_match("a", vec![1_u64], u64::to_string).unwrap(),
+            Some(1)
+        );
+        let err = single_name_match("a", vec![1_u64, 2], u64::to_string).unwrap_err();
+        match err.kind {
+            PitchforkErrorKind::AmbiguousName(name, candidates) => {
+                assert_eq!(name, "a");
+                assert_eq!(candidates, vec!["1", "2"]);
+            }
+            _ => panic!("expected an AmbiguousName error"),
+        }
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let col = |name: &str, column_type: DomoDataType| Column {
+            column_type,
+            name: name.to_string(),
+        };
+        let remote = Schema {
+            columns: vec![
+                col("id", DomoDataType::LONG),
+                col("name", DomoDataType::STRING),
+                col("amount", DomoDataType::LONG),
+                col("old", DomoDataType::STRING),
+            ],
+        };
+        assert!(remote.diff(&remote).is_empty());
+
+        let local = Schema {
+            columns: vec![
+                col("name", DomoDataType::STRING),
+                col("id", DomoDataType::LONG),
+                col("amount", DomoDataType::DECIMAL),
+                col("new", DomoDataType::DATE),
+            ],
+        };
+        let diff = remote.diff(&local);
+        assert_eq!(diff.added, vec![col("new", DomoDataType::DATE)]);
+        assert_eq!(diff.removed, vec![col("old", DomoDataType::STRING)]);
+        assert_eq!(
+            diff.retyped,
+            vec![ColumnTypeChange {
+                name: "amount".to_string(),
+                remote: DomoDataType::LONG,
+                local: DomoDataType::DECIMAL,
+            }]
+        );
+        assert!(diff.reordered);
+        assert!(diff.is_destructive());
+
+        let appended = Schema {
+            columns: vec![
+                col("id", DomoDataType::LONG),
+                col("name", DomoDataType::STRING),
+                col("amount", DomoDataType::LONG),
+                col("old", DomoDataType::STRING),
+                col("new", DomoDataType::DATE),
+            ],
+        };
+        let diff = remote.diff(&appended);
+        assert!(!diff.reordered);
+        assert!(!diff.is_destructive());
+        assert_eq!(diff.to_string(), "added new (DATE)");
+
+        let reordered = Schema {
+            columns: vec![
+                col("name", DomoDataType::STRING),
+                col("id", DomoDataType::LONG),
+                col("amount", DomoDataType::LONG),
+                col("old", DomoDataType::STRING),
+            ],
+        };
+        let diff = remote.diff(&reordered);
+        assert!(diff.reordered);
+        assert!(diff.is_destructive());
+
+        let inserted = Schema {
+            columns: vec![
+                col("id", DomoDataType::LONG),
+                col("new", DomoDataType::DATE),
+                col("name", DomoDataType::STRING),
+                col("amount", DomoDataType::LONG),
+                col("old", DomoDataType::STRING),
+            ],
+        };
+        let diff = remote.diff(&inserted);
+        assert_eq!(diff.added, vec![col("new", DomoDataType::DATE)]);
+        assert!(diff.reordered);
+        assert!(diff.is_destructive());
+
+        let dropped = Schema {
+            columns: remote.columns[..3].to_vec(),
+        };
+        assert!(!remote.diff(&dropped).reordered);
+    }
+
+    #[test]
+    fn test_query_data_deserialize_rows() {
+        #[derive(Deserialize)]
+        struct Row {
+            name: Option<String>,
+            qty: i64,
+            price: f64,
+            day: NaiveDate,
+            at: DateTime<Utc>,
+        }
+        let meta = |t: &str| {
+            json!({
+                "type": t,
+                "dataSourceId": "ds",
+                "maxLength": -1,
+                "minLength": -1,
+                "periodIndex": 0
+            })
+        };
+        let dq: DatasetQueryData = serde_json::from_value(json!({
+            "datasource": "ds",
+            "columns": ["name", "qty", "price", "day", "at"],
+            "metadata": [meta("STRING"), meta("LONG"), meta("DECIMAL"), meta("DATE"), meta("DATETIME")],
+            "rows": [
+                ["a", 1, 1.5, "2019-07-10", "2019-07-10T16:39:57"],
+                [null, "2", "3", "2019-07-11T00:00:00", "2019-07-11 01:02:03"]
+            ],
+            "numRows": 2,
+            "numColumns": 5,
+            "fromcache": false
+        }))
+        .unwrap();
+        let rows: Vec<Row> = dq.deserialize_rows().unwrap();
+        assert_eq!(rows[0].name, Some("a".to_string()));
+        assert_eq!(rows[0].qty, 1);
+        assert_eq!(rows[0].day, NaiveDate::from_ymd_opt(2019, 7, 10).unwrap());
+        assert_eq!(
+            rows[0].at,
+            Utc.with_ymd_and_hms(2019, 7, 10, 16, 39, 57).unwrap()
+        );
+        assert_eq!(rows[1].name, None);
+        assert_eq!(rows[1].qty, 2);
+        assert!((rows[1].price - 3.0).abs() < std::f64::EPSILON);
+        assert_eq!(rows[1].day, NaiveDate::from_ymd_opt(2019, 7, 11).unwrap());
+        assert_eq!(
+            rows[1].at,
+            Utc.with_ymd_and_hms(2019, 7, 11, 1, 2, 3).unwrap()
+        );
+    }
+
     #[test]
     fn test_fieldtype_merge() {
         panic!();
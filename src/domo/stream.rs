@@ -5,25 +5,273 @@
 //! Additional Resources:
 //! - [Domo's Stream API Reference](https://developer.domo.com/docs/streams-api-reference/streams)
 //!
+use crate::domo::dataset::single_name_match;
 use crate::domo::dataset::Dataset;
+use crate::domo::dataset::DatasetListQuery;
 use crate::domo::dataset::DatasetSchema;
+use crate::domo::dataset::DomoDataType;
+use crate::domo::dataset::Schema;
+use crate::domo::stream::split::CsvFilePart;
+use crate::domo::stream::uploader::is_retryable;
 use crate::error::{PitchforkError, PitchforkErrorKind};
+use crate::pitchfork::DomoPitchfork;
 use crate::pitchfork::DomoRequest;
+use crate::pitchfork::DomoRequestBuilder;
 use crate::pitchfork::StreamsRequestBuilder;
 use crate::util::csv::serialize_to_csv_str;
-use log::debug;
-use reqwest::Method;
+use crate::util::progress::{csv_rows, ProgressReader, ProgressTracker};
+use crate::util::schema::{infer_schema, InferSchemaOptions, SchemaInference};
+use crate::util::validate::{validate_csv, ValidateOptions};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use reqwest::{Body, Method};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+pub mod coordinated;
+pub mod history;
+pub mod journal;
+pub mod sink;
+pub mod split;
+pub mod uploader;
+
+/// How a Stream Execution updates the Stream's Dataset.
+/// Methods this crate doesn't know about deserialize into `Unknown`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum UpdateMethod {
     Replace,
     Append,
+    Unknown(String),
+}
+
+impl UpdateMethod {
+    /// The update method name used by the Domo API.
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpdateMethod::Append => "APPEND",
+            UpdateMethod::Replace => "REPLACE",
+            UpdateMethod::Unknown(method) => method,
+        }
+    }
 }
+
+impl From<&str> for UpdateMethod {
+    fn from(method: &str) -> Self {
+        match method {
+            "APPEND" => UpdateMethod::Append,
+            "REPLACE" => UpdateMethod::Replace,
+            other => UpdateMethod::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for UpdateMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for UpdateMethod {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for UpdateMethod {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|s| UpdateMethod::from(s.as_str()))
+    }
+}
+
+/// State of a Stream Execution.
+/// States this crate doesn't know about deserialize into `Unknown`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExecutionState {
+    /// The execution is in progress.
+    Active,
+    Success,
+    Error,
+    Failed,
+    Aborted,
+    Unknown(String),
+}
+
+impl ExecutionState {
+    /// The state name used by the Domo API.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ExecutionState::Active => "ACTIVE",
+            ExecutionState::Success => "SUCCESS",
+            ExecutionState::Error => "ERROR",
+            ExecutionState::Failed => "FAILED",
+            ExecutionState::Aborted => "ABORTED",
+            ExecutionState::Unknown(state) => state,
+        }
+    }
+
+    /// Whether the execution is finished and won't change state again.
+    /// `Unknown` states aren't considered terminal.
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            ExecutionState::Success
+            | ExecutionState::Error
+            | ExecutionState::Failed
+            | ExecutionState::Aborted => true,
+            ExecutionState::Active | ExecutionState::Unknown(_) => false,
+        }
+    }
+
+    /// Whether the execution ended in a failed state.
+    pub fn is_failure(&self) -> bool {
+        self.is_terminal() && *self != ExecutionState::Success
+    }
+}
+
+impl From<&str> for ExecutionState {
+    fn from(state: &str) -> Self {
+        match state {
+            "ACTIVE" => ExecutionState::Active,
+            "SUCCESS" => ExecutionState::Success,
+            "ERROR" => ExecutionState::Error,
+            "FAILED" => ExecutionState::Failed,
+            "ABORTED" => ExecutionState::Aborted,
+            other => ExecutionState::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for ExecutionState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ExecutionState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|s| ExecutionState::from(s.as_str()))
+    }
+}
+
+/// Criteria for a Stream search.
+#[derive(Clone, Debug, PartialEq)]
 pub enum StreamSearchQuery {
     DatasetId(String),
     DatasetOwnerId(u64),
+    DatasetName(String),
+    UpdateMethod(UpdateMethod),
+    /// State of the Stream's last execution.
+    LastExecutionState(ExecutionState),
+    /// Streams that match all of the criteria.
+    And(Vec<StreamSearchQuery>),
+}
+
+impl StreamSearchQuery {
+    /// Combine two criteria so streams have to match both.
+    ///
+    /// # Example
+    /// ```
+    /// use domo_pitchfork::domo::stream::{ExecutionState, StreamSearchQuery};
+    /// let query = StreamSearchQuery::DatasetOwnerId(123)
+    ///     .and(StreamSearchQuery::LastExecutionState(ExecutionState::Error));
+    /// assert_eq!(
+    ///     query.to_query(),
+    ///     "dataSource.owner.id:123 AND lastExecution.currentState:ERROR"
+    /// );
+    /// ```
+    pub fn and(self, other: StreamSearchQuery) -> Self {
+        match self {
+            StreamSearchQuery::And(mut criteria) => {
+                criteria.push(other);
+                StreamSearchQuery::And(criteria)
+            }
+            query => StreamSearchQuery::And(vec![query, other]),
+        }
+    }
+
+    /// The `q` param of a Domo Stream search.
+    pub fn to_query(&self) -> String {
+        match self {
+            StreamSearchQuery::DatasetId(id) => format!("dataSource.id:{}", id),
+            StreamSearchQuery::DatasetOwnerId(user_id) => {
+                format!("dataSource.owner.id:{}", user_id)
+            }
+            StreamSearchQuery::DatasetName(name) => {
+                let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+                format!("dataSource.name:\"{}\"", escaped)
+            }
+            StreamSearchQuery::UpdateMethod(method) => format!("updateMethod:{}", method),
+            StreamSearchQuery::LastExecutionState(state) => {
+                format!("lastExecution.currentState:{}", state)
+            }
+            StreamSearchQuery::And(criteria) => criteria
+                .iter()
+                .map(StreamSearchQuery::to_query)
+                .collect::<Vec<_>>()
+                .join(" AND "),
+        }
+    }
+}
+
+/// A Stream search with field selection and pagination, see
+/// [`search_summaries`](crate::pitchfork::StreamsRequestBuilder::search_summaries).
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamSearch {
+    pub query: StreamSearchQuery,
+    pub limit: u32,
+    pub offset: u32,
+    /// Stream fields to return, e.g. `id` or `dataSet`. Empty uses Domo's default fields.
+    pub fields: Vec<String>,
+}
+
+impl StreamSearch {
+    pub fn new(query: StreamSearchQuery) -> Self {
+        Self {
+            query,
+            limit: 50,
+            offset: 0,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.fields = fields.iter().map(|f| (*f).to_string()).collect();
+        self
+    }
+
+    pub(crate) fn create_query_string(&self) -> String {
+        let mut q = url::form_urlencoded::Serializer::new(String::new());
+        q.append_pair("q", &self.query.to_query());
+        q.append_pair("limit", &self.limit.to_string());
+        q.append_pair("offset", &self.offset.to_string());
+        if !self.fields.is_empty() {
+            q.append_pair("fields", &self.fields.join(","));
+        }
+        q.finish()
+    }
 }
 
 /// Request Builder for Stream API Endpoints
@@ -41,7 +289,6 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
     /// # Ok::<(), PitchforkError>(())
     /// ```
     pub fn info(mut self, stream_id: u64) -> Result<StreamDataset, PitchforkError> {
-        // TODO: there's an optional fields query param now
         self.url.push_str(&stream_id.to_string());
         let req = Self {
             method: Method::GET,
@@ -99,14 +346,10 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
         mut self,
         query: StreamSearchQuery,
     ) -> Result<Vec<StreamDataset>, PitchforkError> {
-        // TODO: optional fields query param
-        let q = match query {
-            StreamSearchQuery::DatasetId(s) => format!("dataSource.id:{}", s),
-            StreamSearchQuery::DatasetOwnerId(user_id) => {
-                format!("dataSource.owner.id:{}", user_id)
-            }
-        };
-        self.url.push_str(&format!("/search?q={}&fields=all", q));
+        let mut q = url::form_urlencoded::Serializer::new(String::new());
+        q.append_pair("q", &query.to_query());
+        q.append_pair("fields", "all");
+        self.url.push_str(&format!("search?{}", q.finish()));
         let req = Self {
             method: Method::GET,
             auth: self.auth,
@@ -119,6 +362,218 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
         Ok(ds_list)
     }
 
+    /// Search Streams returning only the selected fields of one page of results.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::{StreamSearch, StreamSearchQuery, UpdateMethod};
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let query = StreamSearchQuery::DatasetOwnerId(123)
+    ///     .and(StreamSearchQuery::UpdateMethod(UpdateMethod::Append));
+    /// let search = StreamSearch::new(query).limit(100).fields(&["id", "dataSet"]);
+    /// for stream in domo.streams().search_summaries(&search)? {
+    ///     println!("Stream Id: {}", stream.id);
+    /// }
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn search_summaries(
+        mut self,
+        search: &StreamSearch,
+    ) -> Result<Vec<StreamSummary>, PitchforkError> {
+        self.url
+            .push_str(&format!("search?{}", search.create_query_string()));
+        let req = Self {
+            method: Method::GET,
+            auth: self.auth,
+            url: self.url,
+            resp_t: PhantomData,
+            body: None,
+        };
+        let list = serde_json::from_reader(req.send_json()?)?;
+        Ok(list)
+    }
+
+    /// Page through all Streams matching a [`StreamSearch`], starting at its offset.
+    /// Each item of the returned iterator is one page of up to `limit` streams.
+    pub fn paginate_search(self, search: StreamSearch) -> StreamSearchPages<'t> {
+        StreamSearchPages {
+            auth: self.auth,
+            url: self.url,
+            search,
+            done: false,
+        }
+    }
+
+    /// Retrieve only the given fields of a Stream.
+    pub fn info_with_fields(
+        mut self,
+        stream_id: u64,
+        fields: &[&str],
+    ) -> Result<StreamSummary, PitchforkError> {
+        let mut q = url::form_urlencoded::Serializer::new(String::new());
+        q.append_pair("fields", &fields.join(","));
+        self.url.push_str(&format!("{}?{}", stream_id, q.finish()));
+        let req = Self {
+            method: Method::GET,
+            auth: self.auth,
+            url: self.url,
+            resp_t: PhantomData,
+            body: None,
+        };
+        let summary = serde_json::from_reader(req.send_json()?)?;
+        Ok(summary)
+    }
+
+    /// Find the Stream whose Dataset has exactly the given name.
+    /// Returns `Ok(None)` if no Stream Dataset has that name and a
+    /// [`PitchforkErrorKind::AmbiguousName`] error listing the matching stream ids
+    /// if more than one does.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// if let Some(stream) = domo.streams().find_by_dataset_name("Sales Pipeline")? {
+    ///     println!("Stream Id: {}", stream.id);
+    /// }
+    /// # Ok::<(),PitchforkError>(())
+    /// ```
+    pub fn find_by_dataset_name(self, name: &str) -> Result<Option<StreamDataset>, PitchforkError> {
+        let domo = DomoPitchfork::with_token(self.auth);
+        let query = DatasetListQuery::new()
+            .name_like(name)
+            .fields(&["id", "name"]);
+        let mut matches = Vec::new();
+        for page in domo.datasets().paginate(query) {
+            for ds in page? {
+                if ds.name.as_ref().map(String::as_str) != Some(name) {
+                    continue;
+                }
+                matches.extend(domo.streams().search(StreamSearchQuery::DatasetId(ds.id))?);
+            }
+        }
+        single_name_match(name, matches, |s| s.id.to_string())
+    }
+
+    /// Find the Stream whose Dataset is named `ds_meta.dataset_schema.name` or create it
+    /// from `ds_meta` if it doesn't exist.
+    pub fn find_or_create(
+        self,
+        ds_meta: &StreamDatasetSchema,
+    ) -> Result<StreamDataset, PitchforkError> {
+        let domo = DomoPitchfork::with_token(self.auth);
+        match self.find_by_dataset_name(&ds_meta.dataset_schema.name)? {
+            Some(stream) => Ok(stream),
+            None => domo.streams().create(ds_meta),
+        }
+    }
+
+    /// Replace or append to the data of the Stream whose Dataset is named `dataset_name`
+    /// with `records` in a single execution.
+    ///
+    /// If no Stream has a Dataset with that name, one is created with a schema inferred from
+    /// the records. Publishing to an existing Stream fails before anything is uploaded if the
+    /// Stream uses another update method, with a `PitchforkErrorKind::UpdateMethodMismatch`
+    /// error (the method can be changed with [`modify_update_method`](Self::modify_update_method)),
+    /// or if the records don't have the same columns in the same order as its Dataset with
+    /// values that fit the column types.
+    /// If the upload or commit fails the execution is aborted.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::UpdateMethod;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use serde::Serialize;
+    /// #[derive(Serialize)]
+    /// struct Build {
+    ///     project: String,
+    ///     minutes: f64,
+    /// }
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let builds = vec![Build { project: "pitchfork".into(), minutes: 4.5 }];
+    /// let published = domo.streams().publish("CI Builds", &builds, UpdateMethod::Replace)?;
+    /// println!("Published to stream {}", published.stream_id);
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn publish<T: Serialize>(
+        self,
+        dataset_name: &str,
+        records: &[T],
+        update_method: UpdateMethod,
+    ) -> Result<PublishedStream, PitchforkError> {
+        if records.is_empty() {
+            return Err(PitchforkError::new("data is empty"));
+        }
+        let domo = DomoPitchfork::with_token(self.auth);
+        let csv = serialize_to_csv_str(records, true)?;
+        let inferred = infer_schema(csv.as_bytes(), &InferSchemaOptions::new().whole_file())?;
+        let schema = inferred.schema();
+        let (stream, created) = match self.find_by_dataset_name(dataset_name)? {
+            Some(stream) => (stream, false),
+            None => {
+                let ds_meta = StreamDatasetSchema {
+                    dataset_schema: DatasetSchema {
+                        name: dataset_name.to_string(),
+                        description: String::new(),
+                        rows: 0,
+                        schema: schema.clone(),
+                    },
+                    update_method: update_method.clone(),
+                };
+                (domo.streams().create(&ds_meta)?, true)
+            }
+        };
+        if !created {
+            if stream.update_method != update_method {
+                let kind = PitchforkErrorKind::UpdateMethodMismatch(
+                    stream.id,
+                    stream.update_method.clone(),
+                    update_method,
+                );
+                return Err(kind.into());
+            }
+            // the Stream's Dataset summary may leave the schema out
+            let dataset = domo.datasets().info(&stream.dataset.id)?;
+            let remote = dataset.schema.ok_or_else(|| {
+                PitchforkError::new(format!(
+                    "dataset {} of stream {} has no schema to check the records against",
+                    stream.dataset.id, stream.id
+                ))
+            })?;
+            check_publish_schema(stream.id, &remote, &inferred)?;
+        }
+
+        let execution = domo.streams().create_stream_execution(stream.id)?;
+        let result = domo
+            .streams()
+            .upload_serializable_part(stream.id, execution.id, 1, records)
+            .and_then(|_| domo.streams().commit_execution(stream.id, execution.id));
+        match result {
+            Ok(execution) => Ok(PublishedStream {
+                stream_id: stream.id,
+                dataset_id: stream.dataset.id,
+                created,
+                execution,
+            }),
+            Err(e) => {
+                if let Err(abort_err) = domo
+                    .streams()
+                    .abort_stream_execution(stream.id, execution.id)
+                {
+                    warn!(
+                        "failed to abort stream {} execution {}: {}",
+                        stream.id, execution.id, abort_err
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
     /// Create a new `StreamDataset` to create executions and upload data to.
     pub fn create(self, ds_meta: &StreamDatasetSchema) -> Result<StreamDataset, PitchforkError> {
         let body = serde_json::to_string(ds_meta)?;
@@ -165,16 +620,42 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
 
     /// Updates Stream Update Method settings
     pub fn modify_update_method(
-        mut self,
+        self,
         stream_id: u64,
         update_method: &UpdateMethod,
-    ) -> Result<Dataset, PitchforkError> {
+    ) -> Result<StreamDataset, PitchforkError> {
+        self.modify(
+            stream_id,
+            &StreamUpdate::new().update_method(update_method.clone()),
+        )
+    }
+
+    /// Update a Stream's update method and the name, description or schema of its Dataset.
+    /// Only the fields set on the [`StreamUpdate`] are changed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::dataset::{FieldType, Schema};
+    /// use domo_pitchfork::domo::stream::StreamUpdate;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let schema = Schema::from_field_types(&[
+    ///     ("Order Id".to_string(), FieldType::TInteger),
+    ///     ("Region".to_string(), FieldType::TUnicode),
+    /// ]);
+    /// let update = StreamUpdate::new().name("Orders v2").schema(schema);
+    /// let stream = domo.streams().modify(123, &update)?;
+    /// println!("Updated Stream: {}", stream.id);
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn modify(
+        mut self,
+        stream_id: u64,
+        update: &StreamUpdate,
+    ) -> Result<StreamDataset, PitchforkError> {
         self.url.push_str(&stream_id.to_string());
-        let um = match update_method {
-            UpdateMethod::Append => "APPEND",
-            UpdateMethod::Replace => "REPLACE",
-        };
-        let body = json!({ "updateMethod": um }).to_string();
+        let body = serde_json::to_string(update)?;
         debug!("body: {}", body);
         let req = Self {
             method: Method::PATCH,
@@ -183,8 +664,8 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
             resp_t: PhantomData,
             body: Some(body),
         };
-        let ds = serde_json::from_reader(req.send_json()?)?;
-        Ok(ds)
+        let stream = serde_json::from_reader(req.send_json()?)?;
+        Ok(stream)
     }
 
     /// Create a `StreamExecution` to upload data parts to and update the data in Domo.
@@ -337,6 +818,126 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
         Ok(ds_list)
     }
 
+    /// Upload a data part to a stream execution in progress, streaming `len` bytes of csv
+    /// from `reader` as the request body instead of reading them into memory first.
+    /// The data must not include a header row.
+    pub fn upload_part_from_reader<R: Read + Send + 'static>(
+        mut self,
+        stream_id: u64,
+        execution_id: u32,
+        part: u32,
+        reader: R,
+        len: u64,
+    ) -> Result<StreamExecution, PitchforkError> {
+        self.url.push_str(&format!(
+            "{}/executions/{}/part/{}",
+            stream_id, execution_id, part
+        ));
+        let req = Self {
+            method: Method::PUT,
+            auth: self.auth,
+            url: self.url,
+            resp_t: PhantomData,
+            body: None,
+        };
+        let res = req.send_csv_body(Body::sized(reader, len))?;
+        Ok(serde_json::from_reader(res)?)
+    }
+
+    /// Upload a data part like [`upload_part`](Self::upload_part), reporting the bytes
+    /// sent as the part streams to Domo and the part once it's uploaded.
+    ///
+    /// The same [`ProgressTracker`] can be passed for every part of an execution so the
+    /// progress, with the totals it was created with, covers the whole upload. The tracker
+    /// isn't finished, see the example of [`ProgressTracker`].
+    pub fn upload_part_with_progress(
+        self,
+        stream_id: u64,
+        execution_id: u32,
+        part: u32,
+        csv_part: String,
+        tracker: &Arc<ProgressTracker>,
+    ) -> Result<StreamExecution, PitchforkError> {
+        let len = csv_part.len() as u64;
+        let rows = csv_rows(csv_part.as_bytes());
+        let reader = ProgressReader::new(Cursor::new(csv_part.into_bytes()), Arc::clone(tracker));
+        let execution = self.upload_part_from_reader(stream_id, execution_id, part, reader, len)?;
+        tracker.part_done(rows);
+        Ok(execution)
+    }
+
+    /// Upload a whole csv file without a header row as a data part.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let execution = domo.streams().create_stream_execution(123)?;
+    /// domo.streams().upload_part_from_file(123, execution.id, 1, "part1.csv")?;
+    /// domo.streams().upload_part_from_file(123, execution.id, 2, "part2.csv")?;
+    /// domo.streams().commit_execution(123, execution.id)?;
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn upload_part_from_file<P: AsRef<Path>>(
+        self,
+        stream_id: u64,
+        execution_id: u32,
+        part: u32,
+        path: P,
+    ) -> Result<StreamExecution, PitchforkError> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        self.upload_part_from_reader(stream_id, execution_id, part, file, len)
+    }
+
+    /// Upload a range of rows of a csv file as a data part.
+    /// Use [`split_csv_file`](split::split_csv_file) to split a file into ranges.
+    pub fn upload_part_from_file_range<P: AsRef<Path>>(
+        self,
+        stream_id: u64,
+        execution_id: u32,
+        part: u32,
+        path: P,
+        range: &CsvFilePart,
+    ) -> Result<StreamExecution, PitchforkError> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(range.start))?;
+        let reader = file.take(range.len());
+        self.upload_part_from_reader(stream_id, execution_id, part, reader, range.len())
+    }
+
+    /// Validate a csv data part against `schema` and upload it if it's valid.
+    /// Returns a `PitchforkErrorKind::Validation` error with the issues found otherwise.
+    pub fn upload_part_checked(
+        self,
+        stream_id: u64,
+        execution_id: u32,
+        part: u32,
+        csv_part: &str,
+        schema: &Schema,
+    ) -> Result<StreamExecution, PitchforkError> {
+        validate_csv(csv_part, schema, &ValidateOptions::new()).into_result()?;
+        self.upload_part(stream_id, execution_id, part, csv_part)
+    }
+
+    /// Validate a `Serializable` data part against `schema` and upload it if it's valid.
+    /// Returns a `PitchforkErrorKind::Validation` error with the issues found otherwise.
+    pub fn upload_serializable_part_checked<T: Serialize>(
+        self,
+        stream_id: u64,
+        execution_id: u32,
+        part: u32,
+        data: &[T],
+        schema: &Schema,
+    ) -> Result<StreamExecution, PitchforkError> {
+        if data.is_empty() {
+            return Err(PitchforkError::new("data is empty"));
+        }
+        let csv_part = serialize_to_csv_str(&data, false)?;
+        self.upload_part_checked(stream_id, execution_id, part, &csv_part, schema)
+    }
+
     /// Commit a stream execution and finalize insertion of dataparts into Domo Stream Dataset.
     ///
     /// # Example
@@ -368,6 +969,76 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
         Ok(se)
     }
 
+    /// Poll a committed `StreamExecution` until it reaches a terminal state.
+    ///
+    /// Polling starts at `poll_interval`, but no less than 100ms, and backs off up to a
+    /// minute between polls.
+    /// Returns the execution once it succeeded, or an [`ExecutionWaitError`] if it failed,
+    /// didn't finish within `timeout` or couldn't be polled. Pass `Duration::MAX` to wait
+    /// without a timeout.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use std::time::Duration;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let execution = domo.streams().commit_execution(123, 1)?;
+    /// let done = domo.streams().wait_for_execution(
+    ///     123,
+    ///     execution.id,
+    ///     Duration::from_secs(600),
+    ///     Duration::from_secs(2),
+    /// )?;
+    /// println!("Data landed at {:?}", done.ended_at);
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn wait_for_execution(
+        self,
+        stream_id: u64,
+        execution_id: u32,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<StreamExecution, ExecutionWaitError> {
+        const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+        const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+        let domo = DomoPitchfork::with_token(self.auth);
+        // a timeout too long to represent means no deadline
+        let deadline = Instant::now().checked_add(timeout);
+        // a zero interval would poll without pausing
+        let poll_interval = poll_interval.max(MIN_POLL_INTERVAL);
+        let mut delay = poll_interval;
+        let mut last_seen = None;
+        loop {
+            match domo.streams().execution_info(stream_id, execution_id) {
+                Ok(execution) if execution.current_state == ExecutionState::Success => {
+                    return Ok(execution)
+                }
+                Ok(execution) if execution.is_terminal() => {
+                    return Err(ExecutionWaitError::Failed(Box::new(execution)))
+                }
+                Ok(execution) => last_seen = Some(Box::new(execution)),
+                Err(e) if is_retryable(&e) => {
+                    debug!("polling execution {} failed: {}", execution_id, e);
+                }
+                Err(e) => return Err(ExecutionWaitError::Request(e)),
+            }
+            let mut sleep = delay;
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(ExecutionWaitError::TimedOut(last_seen));
+                }
+                sleep = sleep.min(deadline - now);
+            }
+            thread::sleep(sleep);
+            delay = delay
+                .checked_mul(2)
+                .unwrap_or(Duration::MAX)
+                .min(MAX_POLL_INTERVAL.max(poll_interval));
+        }
+    }
+
     /// Abort a stream execution in progress and discard all data parts uploaded to the execution.
     ///
     /// # Example
@@ -398,6 +1069,295 @@ impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
         req.send_json()?;
         Ok(())
     }
+
+    /// All executions of a Stream, paging through [`list_executions`](Self::list_executions).
+    pub fn all_executions(self, stream_id: u64) -> Result<Vec<StreamExecution>, PitchforkError> {
+        const PAGE_SIZE: u32 = 500;
+        let domo = DomoPitchfork::with_token(self.auth);
+        let mut executions = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = domo
+                .streams()
+                .list_executions(stream_id, PAGE_SIZE, offset)?;
+            let len = page.len();
+            executions.extend(page);
+            if len < PAGE_SIZE as usize {
+                return Ok(executions);
+            }
+            offset += PAGE_SIZE;
+        }
+    }
+
+    /// All executions of a Stream that are still `ACTIVE`.
+    ///
+    /// Creating a new execution aborts these, discarding the data uploaded to them.
+    pub fn active_executions(self, stream_id: u64) -> Result<Vec<StreamExecution>, PitchforkError> {
+        let mut executions = self.all_executions(stream_id)?;
+        executions.retain(StreamExecution::is_active);
+        Ok(executions)
+    }
+
+    /// Find the Streams in the instance whose last execution has been `ACTIVE` for
+    /// longer than the scan's threshold, and abort them if the scan asks to.
+    /// A failed abort is recorded in the report rather than stopping the scan.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::StaleExecutionScan;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use std::time::Duration;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let scan = StaleExecutionScan::new(Duration::from_secs(6 * 60 * 60)).abort(true);
+    /// let report = domo.streams().scan_stale_executions(&scan)?;
+    /// println!("{}", report);
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn scan_stale_executions(
+        self,
+        scan: &StaleExecutionScan,
+    ) -> Result<StaleExecutionReport, PitchforkError> {
+        let domo = DomoPitchfork::with_token(self.auth);
+        let older_than =
+            chrono::Duration::from_std(scan.older_than).map_err(PitchforkError::new)?;
+        let search = StreamSearch::new(StreamSearchQuery::LastExecutionState(
+            ExecutionState::Active,
+        ))
+        .limit(500)
+        .fields(&["id", "dataSet", "lastExecution"]);
+        let mut report = StaleExecutionReport {
+            scanned_at: Utc::now(),
+            streams_checked: 0,
+            executions: Vec::new(),
+        };
+        for page in self.paginate_search(search) {
+            for stream in page? {
+                report.streams_checked += 1;
+                if let Some(stale) =
+                    StaleExecution::from_summary(stream, report.scanned_at, older_than)
+                {
+                    report.executions.push(stale);
+                }
+            }
+        }
+        // abort after the search so the search pages don't shift under it
+        if scan.abort {
+            for stale in &mut report.executions {
+                match domo
+                    .streams()
+                    .abort_stream_execution(stale.stream_id, stale.execution.id)
+                {
+                    Ok(()) => stale.aborted = true,
+                    Err(e) => stale.abort_error = Some(e.to_string()),
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Options for [`scan_stale_executions`](crate::pitchfork::StreamsRequestBuilder::scan_stale_executions).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaleExecutionScan {
+    /// Executions active for longer than this are stale.
+    pub older_than: Duration,
+    /// Whether to abort the stale executions found.
+    pub abort: bool,
+}
+
+impl StaleExecutionScan {
+    pub fn new(older_than: Duration) -> Self {
+        Self {
+            older_than,
+            abort: false,
+        }
+    }
+
+    pub fn abort(mut self, abort: bool) -> Self {
+        self.abort = abort;
+        self
+    }
+}
+
+/// An execution that has been active for longer than the scan threshold.
+#[derive(Clone, Debug)]
+pub struct StaleExecution {
+    pub stream_id: u64,
+    pub dataset_id: Option<String>,
+    pub dataset_name: Option<String>,
+    pub execution: StreamExecution,
+    /// Time since the execution started, when the scan ran.
+    pub age: chrono::Duration,
+    pub aborted: bool,
+    /// Why aborting the execution failed.
+    pub abort_error: Option<String>,
+}
+
+impl StaleExecution {
+    fn from_summary(
+        stream: StreamSummary,
+        now: DateTime<Utc>,
+        older_than: chrono::Duration,
+    ) -> Option<Self> {
+        let execution = stream.last_execution.filter(StreamExecution::is_active)?;
+        let age = now - execution.started_at;
+        if age <= older_than {
+            return None;
+        }
+        let (dataset_id, dataset_name) = match stream.dataset {
+            Some(ds) => (Some(ds.id), ds.name),
+            None => (None, None),
+        };
+        Some(Self {
+            stream_id: stream.id,
+            dataset_id,
+            dataset_name,
+            execution,
+            age,
+            aborted: false,
+            abort_error: None,
+        })
+    }
+}
+
+impl fmt::Display for StaleExecution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stream {}", self.stream_id)?;
+        if let Some(name) = &self.dataset_name {
+            write!(f, " ({})", name)?;
+        }
+        write!(
+            f,
+            " execution {} active for {}h{:02}m",
+            self.execution.id,
+            self.age.num_hours(),
+            self.age.num_minutes() % 60
+        )?;
+        if self.aborted {
+            write!(f, ", aborted")?;
+        } else if let Some(e) = &self.abort_error {
+            write!(f, ", abort failed: {}", e)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of a stale execution scan.
+#[derive(Clone, Debug)]
+pub struct StaleExecutionReport {
+    pub scanned_at: DateTime<Utc>,
+    /// Number of Streams with an active last execution.
+    pub streams_checked: usize,
+    pub executions: Vec<StaleExecution>,
+}
+
+impl StaleExecutionReport {
+    /// The stale executions that were aborted.
+    pub fn aborted(&self) -> impl Iterator<Item = &StaleExecution> {
+        self.executions.iter().filter(|e| e.aborted)
+    }
+}
+
+impl fmt::Display for StaleExecutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} stale of {} active executions, {} aborted",
+            self.executions.len(),
+            self.streams_checked,
+            self.aborted().count()
+        )?;
+        for execution in &self.executions {
+            write!(f, "\n  {}", execution)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why [`wait_for_execution`](crate::pitchfork::StreamsRequestBuilder::wait_for_execution)
+/// didn't return a successful execution.
+#[derive(Debug)]
+pub enum ExecutionWaitError {
+    /// The execution ended in a failed state.
+    Failed(Box<StreamExecution>),
+    /// The execution didn't finish in time. Holds the last execution state seen, if any.
+    TimedOut(Option<Box<StreamExecution>>),
+    /// Polling the execution failed.
+    Request(PitchforkError),
+}
+
+impl fmt::Display for ExecutionWaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionWaitError::Failed(execution) => write!(
+                f,
+                "stream execution {} ended with state {}",
+                execution.id, execution.current_state
+            ),
+            ExecutionWaitError::TimedOut(Some(execution)) => write!(
+                f,
+                "timed out waiting for stream execution {} in state {}",
+                execution.id, execution.current_state
+            ),
+            ExecutionWaitError::TimedOut(None) => {
+                write!(f, "timed out waiting for stream execution")
+            }
+            ExecutionWaitError::Request(e) => {
+                write!(f, "failed to poll stream execution: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for ExecutionWaitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExecutionWaitError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ExecutionWaitError> for PitchforkError {
+    fn from(e: ExecutionWaitError) -> Self {
+        PitchforkError::new(e)
+    }
+}
+
+/// Iterator over the pages of a Stream search, see
+/// [`paginate_search`](crate::pitchfork::StreamsRequestBuilder::paginate_search).
+pub struct StreamSearchPages<'t> {
+    auth: &'t str,
+    url: String,
+    search: StreamSearch,
+    done: bool,
+}
+
+impl<'t> Iterator for StreamSearchPages<'t> {
+    type Item = Result<Vec<StreamSummary>, PitchforkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let req: StreamsRequestBuilder<'t, StreamDataset> =
+            DomoRequestBuilder::new(self.auth, self.url.clone()).into();
+        let page = req.search_summaries(&self.search);
+        match &page {
+            Ok(streams) => {
+                if streams.len() < self.search.limit as usize || self.search.limit == 0 {
+                    self.done = true;
+                }
+                if streams.is_empty() {
+                    return None;
+                }
+                self.search.offset += self.search.limit;
+            }
+            Err(_) => self.done = true,
+        }
+        Some(page)
+    }
 }
 
 // [Stream Object](https://developer.domo.com/docs/streams-api-reference/streams
@@ -407,11 +1367,11 @@ pub struct StreamDataset {
     #[serde(rename = "dataSet")]
     pub dataset: Dataset,
     #[serde(rename = "updateMethod")]
-    pub update_method: String,
+    pub update_method: UpdateMethod,
     #[serde(rename = "createdAt")]
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
     #[serde(rename = "modifiedAt")]
-    pub modified_at: String,
+    pub modified_at: DateTime<Utc>,
     #[serde(rename = "lastExecution")]
     pub last_execution: Option<StreamExecution>,
 }
@@ -420,15 +1380,47 @@ pub struct StreamDataset {
 pub struct StreamExecution {
     pub id: u32,
     #[serde(rename = "startedAt")]
-    pub started_at: String,
+    pub started_at: DateTime<Utc>,
     #[serde(rename = "endedAt")]
-    pub ended_at: Option<String>,
+    pub ended_at: Option<DateTime<Utc>>,
     #[serde(rename = "currentState")]
-    pub current_state: String,
+    pub current_state: ExecutionState,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "modifiedAt")]
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// A Stream with only the fields selected in a search or info request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamSummary {
+    pub id: u64,
+    #[serde(rename = "dataSet")]
+    pub dataset: Option<Dataset>,
+    #[serde(rename = "updateMethod")]
+    pub update_method: Option<UpdateMethod>,
     #[serde(rename = "createdAt")]
-    pub created_at: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "modifiedAt")]
-    pub modified_at: Option<String>,
+    pub modified_at: Option<DateTime<Utc>>,
+    #[serde(rename = "lastExecution")]
+    pub last_execution: Option<StreamExecution>,
+}
+
+impl StreamExecution {
+    /// Time from the start to the end of the execution, `None` while it hasn't ended.
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.ended_at.map(|ended| ended - self.started_at)
+    }
+
+    /// See [`ExecutionState::is_terminal`].
+    pub fn is_terminal(&self) -> bool {
+        self.current_state.is_terminal()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current_state == ExecutionState::Active
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -436,7 +1428,124 @@ pub struct StreamDatasetSchema {
     #[serde(rename = "dataSet")]
     pub dataset_schema: DatasetSchema,
     #[serde(rename = "updateMethod")]
-    pub update_method: String,
+    pub update_method: UpdateMethod,
+}
+
+/// Result of [`publish`](crate::pitchfork::StreamsRequestBuilder::publish).
+#[derive(Clone, Debug)]
+pub struct PublishedStream {
+    pub stream_id: u64,
+    pub dataset_id: String,
+    /// Whether the Stream was created by the publish.
+    pub created: bool,
+    /// The committed execution.
+    pub execution: StreamExecution,
+}
+
+/// Records can only be published to a Stream whose Dataset has the same columns in the
+/// same order. The types inferred from the values may be narrower than the column types,
+/// like whole numbers in a `DOUBLE` column, and columns without values fit any type.
+fn check_publish_schema(
+    stream_id: u64,
+    remote: &Schema,
+    inferred: &SchemaInference,
+) -> Result<(), PitchforkError> {
+    let diff = remote.diff(&inferred.schema());
+    if !diff.added.is_empty() || !diff.removed.is_empty() || diff.reordered {
+        return Err(PitchforkError::new(format!(
+            "records don't match the columns of stream {}: {}",
+            stream_id, diff
+        )));
+    }
+    let mismatched: Vec<String> = remote
+        .columns
+        .iter()
+        .zip(&inferred.columns)
+        .filter(|(col, values)| values.non_null > 0 && !fits(&col.column_type, &values.domo_type()))
+        .map(|(col, values)| {
+            format!(
+                "{} ({} values in a {} column)",
+                col.name,
+                values.domo_type(),
+                col.column_type
+            )
+        })
+        .collect();
+    if mismatched.is_empty() {
+        Ok(())
+    } else {
+        Err(PitchforkError::new(format!(
+            "records don't match the column types of stream {}: {}",
+            stream_id,
+            mismatched.join(", ")
+        )))
+    }
+}
+
+/// Whether values of type `values` can be loaded into a column of type `column`.
+fn fits(column: &DomoDataType, values: &DomoDataType) -> bool {
+    column == values
+        || match column {
+            DomoDataType::STRING | DomoDataType::Unknown(_) => true,
+            DomoDataType::DECIMAL | DomoDataType::DOUBLE => matches!(
+                values,
+                DomoDataType::LONG | DomoDataType::DECIMAL | DomoDataType::DOUBLE
+            ),
+            DomoDataType::DATETIME => *values == DomoDataType::DATE,
+            _ => false,
+        }
+}
+
+/// Changes to a Stream for [`modify`](crate::pitchfork::StreamsRequestBuilder::modify).
+/// Fields that are `None` are left unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct StreamUpdate {
+    #[serde(rename = "dataSet", skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<StreamDatasetUpdate>,
+    #[serde(rename = "updateMethod", skip_serializing_if = "Option::is_none")]
+    pub update_method: Option<UpdateMethod>,
+}
+
+/// Changes to the Dataset of a Stream.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct StreamDatasetUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Schema>,
+}
+
+impl StreamUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update_method(mut self, update_method: UpdateMethod) -> Self {
+        self.update_method = Some(update_method);
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.dataset_mut().name = Some(name.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.dataset_mut().description = Some(description.to_string());
+        self
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.dataset_mut().schema = Some(schema);
+        self
+    }
+
+    fn dataset_mut(&mut self) -> &mut StreamDatasetUpdate {
+        self.dataset
+            .get_or_insert_with(StreamDatasetUpdate::default)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -445,8 +1554,6 @@ pub struct NewStreamDataset {
     pub dataset: StreamDatasetSchema,
 }
 
-//TODO: impl UpdateMethod
-
 // #[cfg(test)]
 // mod tests {
 //     use super::*;
@@ -506,3 +1613,159 @@ pub struct NewStreamDataset {
 //     }
 
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_stream_execution_deserialize() {
+        let execution: StreamExecution = serde_json::from_value(json!({
+            "id": 1,
+            "startedAt": "2016-06-21T17:20:36Z",
+            "endedAt": "2016-06-21T17:22:06Z",
+            "currentState": "SUCCESS",
+            "createdAt": "2016-06-21T17:20:36Z",
+            "modifiedAt": "2016-06-21T17:22:06Z"
+        }))
+        .unwrap();
+        assert_eq!(execution.current_state, ExecutionState::Success);
+        assert!(execution.is_terminal());
+        assert_eq!(execution.duration(), Some(chrono::Duration::seconds(90)));
+
+        let active: StreamExecution = serde_json::from_value(json!({
+            "id": 2,
+            "startedAt": "2016-06-21T17:20:36Z",
+            "currentState": "UPLOADING"
+        }))
+        .unwrap();
+        assert_eq!(
+            active.current_state,
+            ExecutionState::Unknown("UPLOADING".to_string())
+        );
+        assert!(!active.is_terminal());
+        assert_eq!(active.duration(), None);
+    }
+
+    #[test]
+    fn test_stream_search_query_string() {
+        let search = StreamSearch::new(
+            StreamSearchQuery::DatasetName("Sales \"EU\"".to_string())
+                .and(StreamSearchQuery::UpdateMethod(UpdateMethod::Replace))
+                .and(StreamSearchQuery::DatasetOwnerId(7)),
+        )
+        .limit(10)
+        .offset(20)
+        .fields(&["id", "dataSet"]);
+        assert_eq!(
+            search.query.to_query(),
+            "dataSource.name:\"Sales \\\"EU\\\"\" AND updateMethod:REPLACE AND dataSource.owner.id:7"
+        );
+        assert_eq!(
+            search.create_query_string(),
+            "q=dataSource.name%3A%22Sales+%5C%22EU%5C%22%22+AND+updateMethod%3AREPLACE+AND+dataSource.owner.id%3A7&limit=10&offset=20&fields=id%2CdataSet"
+        );
+        assert_eq!(
+            StreamSearchQuery::DatasetName("C:\\exports\\\"q\"".to_string()).to_query(),
+            "dataSource.name:\"C:\\\\exports\\\\\\\"q\\\"\""
+        );
+    }
+
+    #[test]
+    fn test_stream_summary_partial_fields() {
+        let summary: StreamSummary =
+            serde_json::from_value(json!({"id": 42, "dataSet": {"id": "abc", "name": "Sales"}}))
+                .unwrap();
+        assert_eq!(summary.id, 42);
+        assert_eq!(summary.dataset.unwrap().name.unwrap(), "Sales");
+        assert!(summary.last_execution.is_none());
+    }
+
+    #[test]
+    fn test_stale_execution() {
+        let summary = |state: &str| -> StreamSummary {
+            serde_json::from_value(json!({
+                "id": 7,
+                "dataSet": {"id": "abc", "name": "Sales"},
+                "lastExecution": {
+                    "id": 3,
+                    "startedAt": "2019-07-10T10:00:00Z",
+                    "currentState": state
+                }
+            }))
+            .unwrap()
+        };
+        let now = "2019-07-10T16:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        let threshold = chrono::Duration::hours(6);
+        let stale = StaleExecution::from_summary(summary("ACTIVE"), now, threshold).unwrap();
+        assert_eq!(stale.dataset_id.as_ref().unwrap(), "abc");
+        assert_eq!(stale.age, chrono::Duration::minutes(390));
+        assert_eq!(
+            stale.to_string(),
+            "stream 7 (Sales) execution 3 active for 6h30m"
+        );
+        assert!(StaleExecution::from_summary(summary("SUCCESS"), now, threshold).is_none());
+        assert!(
+            StaleExecution::from_summary(summary("ACTIVE"), now, chrono::Duration::hours(7))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_check_publish_schema() {
+        use crate::domo::dataset::FieldType;
+        let remote = Schema::from_field_types(&[
+            ("project".to_string(), FieldType::TUnicode),
+            ("minutes".to_string(), FieldType::TFloat),
+            ("runs".to_string(), FieldType::TInteger),
+        ]);
+        let check = |csv: &str| {
+            let inferred = infer_schema(csv.as_bytes(), &InferSchemaOptions::new()).unwrap();
+            check_publish_schema(1, &remote, &inferred)
+        };
+        // whole numbers fit the DECIMAL column and an empty column fits any type
+        assert!(check("project,minutes,runs\npitchfork,4,\n").is_ok());
+        let err = check("project,minutes,runs\npitchfork,4.5,1.5\n").unwrap_err();
+        let msg = err.source().unwrap().to_string();
+        assert!(
+            msg.contains("runs (DECIMAL values in a LONG column)"),
+            "{}",
+            msg
+        );
+        assert!(check("minutes,project,runs\n4.5,pitchfork,1\n").is_err());
+        let err = check("project,minutes,runs,branch\npitchfork,4.5,1,main\n").unwrap_err();
+        let msg = err.source().unwrap().to_string();
+        assert!(msg.contains("added branch"), "{}", msg);
+    }
+
+    #[test]
+    fn test_stream_update_serialization() {
+        let update = StreamUpdate::new().update_method(UpdateMethod::Append);
+        assert_eq!(json!(update), json!({ "updateMethod": "APPEND" }));
+
+        let schema = Schema::from_field_types(&[(
+            "id".to_string(),
+            crate::domo::dataset::FieldType::TInteger,
+        )]);
+        let update = StreamUpdate::new().description("Orders").schema(schema);
+        assert_eq!(
+            json!(update),
+            json!({
+                "dataSet": {
+                    "description": "Orders",
+                    "schema": { "columns": [{ "type": "LONG", "name": "id" }] }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_method_serde() {
+        assert_eq!(json!(UpdateMethod::Append), json!("APPEND"));
+        let method: UpdateMethod = serde_json::from_value(json!("REPLACE")).unwrap();
+        assert_eq!(method, UpdateMethod::Replace);
+        let method: UpdateMethod = serde_json::from_value(json!("UPSERT")).unwrap();
+        assert_eq!(method.as_str(), "UPSERT");
+    }
+}
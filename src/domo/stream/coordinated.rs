@@ -0,0 +1,456 @@
+//! Load data into several Streams so that either all of them are committed or none are.
+//!
+//! A [`CoordinatedLoad`] splits the data of every Stream into parts before touching Domo,
+//! then opens an execution on each Stream, uploads all parts and only commits the
+//! executions once every upload has succeeded. If anything fails before the commits, every
+//! open execution is aborted. Domo has no multi-stream transaction, so a commit that fails
+//! after other Streams were committed can't undo those commits; the error lists them.
+use super::uploader::{is_retryable, retry_backoff, CsvPart, CsvParts, UploadStage};
+use super::StreamExecution;
+use crate::error::PitchforkError;
+use crate::pitchfork::{DomoPitchfork, StreamsRequestBuilder};
+use crate::util::csv::serialize_to_csv_str;
+use log::{debug, warn};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Uploads data to several Streams and commits them together.
+///
+/// # Example
+/// ```no_run
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::pitchfork::DomoPitchfork;
+/// use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Sale {
+///     product_id: u32,
+///     amount: f64,
+/// }
+/// #[derive(Serialize)]
+/// struct Product {
+///     id: u32,
+///     name: String,
+/// }
+/// let sales = vec![Sale { product_id: 1, amount: 9.99 }];
+/// let products = vec![Product { id: 1, name: "Pitchfork".into() }];
+/// let domo = DomoPitchfork::with_token("token");
+/// let report = domo
+///     .streams()
+///     .coordinated_load()
+///     .records(101, &sales)
+///     .records(102, &products)
+///     .run()?;
+/// for stream in &report.streams {
+///     println!("stream {} committed {} rows", stream.stream_id, stream.rows);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct CoordinatedLoad<'t> {
+    auth: &'t str,
+    streams: Vec<LoadStream>,
+    part_size: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+#[derive(Debug)]
+struct LoadStream {
+    stream_id: u64,
+    data: Vec<LoadData>,
+}
+
+#[derive(Debug)]
+enum LoadData {
+    Csv {
+        csv: String,
+        has_headers: bool,
+    },
+    /// Records that couldn't be serialized, reported when the load runs.
+    Invalid(PitchforkError),
+}
+
+impl<'t> fmt::Debug for CoordinatedLoad<'t> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stream_ids: Vec<u64> = self.streams.iter().map(|s| s.stream_id).collect();
+        f.debug_struct("CoordinatedLoad")
+            .field("auth", &"[REDACTED]")
+            .field("streams", &stream_ids)
+            .field("part_size", &self.part_size)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay", &self.retry_delay)
+            .finish()
+    }
+}
+
+impl<'t> StreamsRequestBuilder<'t, super::StreamDataset> {
+    /// Create a [`CoordinatedLoad`] to upload to several Streams and commit them together.
+    pub fn coordinated_load(self) -> CoordinatedLoad<'t> {
+        CoordinatedLoad::new(self.auth)
+    }
+}
+
+impl<'t> CoordinatedLoad<'t> {
+    pub fn new(auth: &'t str) -> Self {
+        Self {
+            auth,
+            streams: Vec::new(),
+            part_size: 10 * 1024 * 1024,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Target size in bytes of each data part.
+    pub fn part_size(mut self, bytes: usize) -> Self {
+        self.part_size = bytes.max(1);
+        self
+    }
+
+    /// Number of times a failed part upload is retried.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Delay before the first retry of a part. The delay doubles with each retry.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Add csv data for a Stream. Data added for the same Stream more than once is
+    /// uploaded to the same execution.
+    pub fn csv(self, stream_id: u64, csv: String, has_headers: bool) -> Self {
+        self.add(stream_id, LoadData::Csv { csv, has_headers })
+    }
+
+    /// Add `Serialize` records for a Stream.
+    pub fn records<T: Serialize>(self, stream_id: u64, records: &[T]) -> Self {
+        let data = match serialize_to_csv_str(records, false) {
+            Ok(csv) => LoadData::Csv {
+                csv,
+                has_headers: false,
+            },
+            Err(e) => LoadData::Invalid(e),
+        };
+        self.add(stream_id, data)
+    }
+
+    fn add(mut self, stream_id: u64, data: LoadData) -> Self {
+        match self.streams.iter_mut().find(|s| s.stream_id == stream_id) {
+            Some(stream) => stream.data.push(data),
+            None => self.streams.push(LoadStream {
+                stream_id,
+                data: vec![data],
+            }),
+        }
+        self
+    }
+
+    /// Upload the data of every Stream and commit all of them, or abort all of them if any
+    /// stage fails before the commits.
+    pub fn run(mut self) -> Result<CoordinatedLoadReport, CoordinatedLoadError> {
+        let start = Instant::now();
+        let prepared = self.split()?;
+
+        let mut open: Vec<(u64, u32)> = Vec::new();
+        for (stream_id, _) in &prepared {
+            match self.streams().create_stream_execution(*stream_id) {
+                Ok(execution) => open.push((*stream_id, execution.id)),
+                Err(e) => {
+                    return Err(self.fail(*stream_id, UploadStage::CreateExecution, e, &open))
+                }
+            }
+        }
+        debug!("coordinated load opened executions {:?}", open);
+
+        for ((stream_id, parts), (_, execution_id)) in prepared.iter().zip(&open) {
+            for (i, part) in parts.iter().enumerate() {
+                let part_num = i as u32 + 1;
+                if let Err(e) = self.upload_with_retries(*stream_id, *execution_id, part_num, part)
+                {
+                    return Err(self.fail(*stream_id, UploadStage::UploadPart(part_num), e, &open));
+                }
+            }
+        }
+
+        let mut streams = Vec::new();
+        for (i, ((stream_id, parts), (_, execution_id))) in prepared.iter().zip(&open).enumerate() {
+            match self.streams().commit_execution(*stream_id, *execution_id) {
+                Ok(execution) => streams.push(StreamLoad {
+                    stream_id: *stream_id,
+                    execution,
+                    parts: parts.len() as u32,
+                    rows: parts.iter().map(|p| p.rows).sum(),
+                    bytes: parts.iter().map(|p| p.csv.len() as u64).sum(),
+                }),
+                Err(e) => {
+                    let mut err = self.fail(*stream_id, UploadStage::Commit, e, &open[i..]);
+                    let committed = streams
+                        .iter()
+                        .map(|s| (s.stream_id, LoadOutcome::Committed));
+                    err.streams.splice(0..0, committed);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(CoordinatedLoadReport {
+            streams,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    /// Split the data of every Stream into parts before any execution is opened.
+    fn split(&mut self) -> Result<Vec<(u64, Vec<CsvPart>)>, CoordinatedLoadError> {
+        let split_error = |stream_id, source| CoordinatedLoadError {
+            stream_id,
+            stage: UploadStage::Split,
+            streams: Vec::new(),
+            source,
+        };
+        let mut prepared = Vec::new();
+        for stream in &mut self.streams {
+            let stream_id = stream.stream_id;
+            let mut parts = Vec::new();
+            for data in stream.data.drain(..) {
+                match data {
+                    LoadData::Csv { csv, has_headers } => {
+                        for part in CsvParts::new(csv.as_bytes(), has_headers, self.part_size) {
+                            parts.push(part.map_err(|e| split_error(stream_id, e))?);
+                        }
+                    }
+                    LoadData::Invalid(e) => return Err(split_error(stream_id, e)),
+                }
+            }
+            // committing an empty execution would clear a REPLACE Stream
+            if parts.is_empty() {
+                return Err(split_error(stream_id, PitchforkError::new("data is empty")));
+            }
+            prepared.push((stream_id, parts));
+        }
+        Ok(prepared)
+    }
+
+    /// Abort the `open` executions after a failure.
+    fn fail(
+        &self,
+        stream_id: u64,
+        stage: UploadStage,
+        source: PitchforkError,
+        open: &[(u64, u32)],
+    ) -> CoordinatedLoadError {
+        let mut err = CoordinatedLoadError {
+            stream_id,
+            stage,
+            streams: Vec::new(),
+            source,
+        };
+        for (open_stream, execution_id) in open {
+            match self
+                .streams()
+                .abort_stream_execution(*open_stream, *execution_id)
+            {
+                Ok(()) => err.streams.push((*open_stream, LoadOutcome::Aborted)),
+                Err(e) => {
+                    warn!(
+                        "failed to abort stream {} execution {}: {}",
+                        open_stream, execution_id, e
+                    );
+                    err.streams.push((*open_stream, LoadOutcome::AbortFailed));
+                }
+            }
+        }
+        err
+    }
+
+    fn upload_with_retries(
+        &self,
+        stream_id: u64,
+        execution_id: u32,
+        part_num: u32,
+        part: &CsvPart,
+    ) -> Result<(), PitchforkError> {
+        let mut retries = 0;
+        loop {
+            match self
+                .streams()
+                .upload_part(stream_id, execution_id, part_num, &part.csv)
+            {
+                Ok(_) => return Ok(()),
+                Err(e) if retries < self.max_retries && is_retryable(&e) => {
+                    let delay = retry_backoff(self.retry_delay, retries);
+                    warn!(
+                        "retrying part {} of stream {} execution {} in {:?}: {}",
+                        part_num, stream_id, execution_id, delay, e
+                    );
+                    thread::sleep(delay);
+                    retries += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn streams(&self) -> StreamsRequestBuilder<'t, super::StreamDataset> {
+        DomoPitchfork::with_token(self.auth).streams()
+    }
+}
+
+/// A Stream committed by a [`CoordinatedLoad`].
+#[derive(Clone, Debug)]
+pub struct StreamLoad {
+    pub stream_id: u64,
+    /// The committed Stream Execution.
+    pub execution: StreamExecution,
+    pub parts: u32,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+/// Result of a successful [`CoordinatedLoad`].
+#[derive(Clone, Debug)]
+pub struct CoordinatedLoadReport {
+    /// The committed Streams, in the order they were added.
+    pub streams: Vec<StreamLoad>,
+    pub elapsed: Duration,
+}
+
+/// A failed [`CoordinatedLoad`].
+#[derive(Debug)]
+pub struct CoordinatedLoadError {
+    /// The Stream the failing stage was working on.
+    pub stream_id: u64,
+    pub stage: UploadStage,
+    /// What happened to each Stream that had an execution open when the load failed.
+    pub streams: Vec<(u64, LoadOutcome)>,
+    pub source: PitchforkError,
+}
+
+/// State a Stream was left in by a failed [`CoordinatedLoad`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadOutcome {
+    /// The execution was aborted, the Stream's data is unchanged.
+    Aborted,
+    /// The execution couldn't be aborted and may still be active.
+    AbortFailed,
+    /// The execution was committed before a later commit failed. It can't be rolled back.
+    Committed,
+}
+
+impl CoordinatedLoadError {
+    /// The Streams left in the given state.
+    pub fn streams_with(&self, outcome: LoadOutcome) -> Vec<u64> {
+        self.streams
+            .iter()
+            .filter(|(_, o)| *o == outcome)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+impl fmt::Display for CoordinatedLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "coordinated load failed on stream {} {}: {}",
+            self.stream_id, self.stage, self.source
+        )?;
+        let outcomes = [
+            (LoadOutcome::Aborted, "aborted streams"),
+            (LoadOutcome::AbortFailed, "could not abort streams"),
+            (LoadOutcome::Committed, "already committed streams"),
+        ];
+        for (outcome, label) in &outcomes {
+            let streams = self.streams_with(*outcome);
+            if !streams.is_empty() {
+                write!(f, " ({} {:?})", label, streams)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for CoordinatedLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<CoordinatedLoadError> for PitchforkError {
+    fn from(e: CoordinatedLoadError) -> Self {
+        PitchforkError::new(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PitchforkErrorKind;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    #[test]
+    fn test_coordinated_load_debug_redacts_token() {
+        let load = CoordinatedLoad::new("SECRET-TOKEN-123").csv(7, "a,1\n".to_string(), false);
+        let debug = format!("{:?}", load);
+        assert!(!debug.contains("SECRET-TOKEN-123"));
+        assert!(debug.contains("streams: [7]"));
+    }
+
+    #[test]
+    fn test_coordinated_load_split() {
+        let mut load = CoordinatedLoad::new("token")
+            .part_size(8)
+            .records(1, &[Row { id: 1, name: "a" }, Row { id: 2, name: "b" }])
+            .csv(2, "id,name\n3,c\n".to_string(), true)
+            .records(1, &[Row { id: 4, name: "d" }]);
+        let prepared = load.split().unwrap();
+        let csvs: Vec<(u64, Vec<&str>)> = prepared
+            .iter()
+            .map(|(id, parts)| (*id, parts.iter().map(|p| p.csv.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            csvs,
+            vec![(1, vec!["1,a\n2,b\n", "4,d\n"]), (2, vec!["3,c\n"])]
+        );
+    }
+
+    #[test]
+    fn test_coordinated_load_error_display() {
+        let err = CoordinatedLoadError {
+            stream_id: 3,
+            stage: UploadStage::Commit,
+            streams: vec![
+                (1, LoadOutcome::Committed),
+                (3, LoadOutcome::Aborted),
+                (4, LoadOutcome::AbortFailed),
+            ],
+            source: PitchforkErrorKind::DomoBadRequest(500, "boom".to_string()).into(),
+        };
+        assert_eq!(err.streams_with(LoadOutcome::Committed), vec![1]);
+        assert_eq!(
+            err.to_string(),
+            "coordinated load failed on stream 3 committing stream execution: \
+             HTTP 500: boom (aborted streams [3]) (could not abort streams [4]) \
+             (already committed streams [1])"
+        );
+    }
+
+    #[test]
+    fn test_coordinated_load_empty_stream_fails_before_executions() {
+        let err = CoordinatedLoad::new("token")
+            .records(1, &[Row { id: 1, name: "a" }])
+            .csv(2, "id,name\n".to_string(), true)
+            .run()
+            .unwrap_err();
+        assert_eq!((err.stream_id, err.stage), (2, UploadStage::Split));
+        assert!(err.streams.is_empty());
+    }
+}
@@ -0,0 +1,349 @@
+//! Success rates, durations and freshness of Stream Executions over a time window.
+use super::{ExecutionState, StreamDataset, StreamExecution};
+use crate::error::{PitchforkError, PitchforkErrorKind};
+use crate::pitchfork::{DomoPitchfork, StreamsRequestBuilder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+/// Options for an execution history report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionHistoryOptions {
+    /// Only executions started within this long before the report are counted.
+    pub window: Duration,
+    /// A Stream is stale if its last successful execution ended longer ago than this.
+    pub freshness: Option<Duration>,
+}
+
+impl ExecutionHistoryOptions {
+    /// Count the executions started within `window` before the report, without a freshness check.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            freshness: None,
+        }
+    }
+
+    /// Mark a Stream stale if it had no successful execution within `freshness`.
+    pub fn freshness(mut self, freshness: Duration) -> Self {
+        self.freshness = Some(freshness);
+        self
+    }
+}
+
+/// Execution statistics of a Stream. The fields are flat so a list of reports
+/// serializes to csv as well as json.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionHistoryReport {
+    pub stream_id: u64,
+    pub dataset_id: Option<String>,
+    pub dataset_name: Option<String>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    /// Executions started within the window.
+    pub executions: u32,
+    pub successes: u32,
+    pub failures: u32,
+    /// Executions still in progress.
+    pub active: u32,
+    /// Successes out of finished executions, `None` if none finished.
+    pub success_rate: Option<f64>,
+    /// Duration percentiles in seconds of the successful executions in the window.
+    pub duration_p50_secs: Option<f64>,
+    pub duration_p90_secs: Option<f64>,
+    pub duration_p99_secs: Option<f64>,
+    pub duration_max_secs: Option<f64>,
+    /// When the last successful execution ended, even if it was before the window.
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_success_execution_id: Option<u32>,
+    /// Whether the Stream has had no successful execution within the freshness window.
+    /// Always false when the report has no freshness window.
+    pub stale: bool,
+    /// Why the Stream's executions couldn't be listed. The statistics are empty if set.
+    pub error: Option<String>,
+}
+
+impl ExecutionHistoryReport {
+    /// Compute the report from all known executions of a Stream.
+    pub fn from_executions(
+        stream_id: u64,
+        executions: &[StreamExecution],
+        options: &ExecutionHistoryOptions,
+        now: DateTime<Utc>,
+    ) -> Result<Self, PitchforkError> {
+        let freshness = options
+            .freshness
+            .map(chrono::Duration::from_std)
+            .transpose()
+            .map_err(PitchforkError::new)?;
+        let mut report = Self::empty(stream_id, options, now)?;
+        let window_start = report.window_start;
+        let mut durations = Vec::new();
+        for execution in executions {
+            if execution.current_state == ExecutionState::Success {
+                let ended = execution.ended_at.unwrap_or(execution.started_at);
+                if report.last_success.map_or(true, |last| ended > last) {
+                    report.last_success = Some(ended);
+                    report.last_success_execution_id = Some(execution.id);
+                }
+            }
+            if execution.started_at < window_start || execution.started_at > now {
+                continue;
+            }
+            report.executions += 1;
+            if execution.current_state == ExecutionState::Success {
+                report.successes += 1;
+                if let Some(duration) = execution.duration() {
+                    durations.push(duration.num_milliseconds() as f64 / 1000.0);
+                }
+            } else if execution.current_state.is_failure() {
+                report.failures += 1;
+            } else if execution.is_active() {
+                report.active += 1;
+            }
+        }
+        let finished = report.successes + report.failures;
+        if finished > 0 {
+            report.success_rate = Some(f64::from(report.successes) / f64::from(finished));
+        }
+        durations.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        report.duration_p50_secs = percentile(&durations, 50.0);
+        report.duration_p90_secs = percentile(&durations, 90.0);
+        report.duration_p99_secs = percentile(&durations, 99.0);
+        report.duration_max_secs = durations.last().copied();
+        if let Some(freshness) = freshness {
+            report.stale = report
+                .last_success
+                .map_or(true, |last| now - last > freshness);
+        }
+        Ok(report)
+    }
+
+    /// A report for a Stream whose executions couldn't be listed.
+    pub fn from_error(
+        stream_id: u64,
+        error: &PitchforkError,
+        options: &ExecutionHistoryOptions,
+        now: DateTime<Utc>,
+    ) -> Result<Self, PitchforkError> {
+        let mut report = Self::empty(stream_id, options, now)?;
+        // the message of an error without a specific kind is in its source
+        report.error = Some(match (&error.kind, error.source()) {
+            (PitchforkErrorKind::Unknown, Some(source)) => source.to_string(),
+            (_, Some(source)) => format!("{}: {}", error, source),
+            (_, None) => error.to_string(),
+        });
+        Ok(report)
+    }
+
+    fn empty(
+        stream_id: u64,
+        options: &ExecutionHistoryOptions,
+        now: DateTime<Utc>,
+    ) -> Result<Self, PitchforkError> {
+        let window = chrono::Duration::from_std(options.window).map_err(PitchforkError::new)?;
+        Ok(Self {
+            stream_id,
+            dataset_id: None,
+            dataset_name: None,
+            window_start: now - window,
+            window_end: now,
+            executions: 0,
+            successes: 0,
+            failures: 0,
+            active: 0,
+            success_rate: None,
+            duration_p50_secs: None,
+            duration_p90_secs: None,
+            duration_p99_secs: None,
+            duration_max_secs: None,
+            last_success: None,
+            last_success_execution_id: None,
+            stale: false,
+            error: None,
+        })
+    }
+
+    fn with_dataset(mut self, stream: &StreamDataset) -> Self {
+        self.dataset_id = Some(stream.dataset.id.clone());
+        self.dataset_name = stream.dataset.name.clone();
+        self
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.max(1).min(sorted.len()) - 1])
+}
+
+impl<'t> StreamsRequestBuilder<'t, StreamDataset> {
+    /// Report the success rate, duration percentiles and freshness of a Stream's executions.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::history::ExecutionHistoryOptions;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use std::time::Duration;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let options = ExecutionHistoryOptions::new(Duration::from_secs(7 * 24 * 60 * 60))
+    ///     .freshness(Duration::from_secs(24 * 60 * 60));
+    /// let report = domo.streams().execution_history(123, &options)?;
+    /// println!("{}", serde_json::to_string_pretty(&report)?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn execution_history(
+        self,
+        stream_id: u64,
+        options: &ExecutionHistoryOptions,
+    ) -> Result<ExecutionHistoryReport, PitchforkError> {
+        let domo = DomoPitchfork::with_token(self.auth);
+        let stream = domo.streams().info(stream_id)?;
+        let executions = domo.streams().all_executions(stream_id)?;
+        Ok(
+            ExecutionHistoryReport::from_executions(stream_id, &executions, options, Utc::now())?
+                .with_dataset(&stream),
+        )
+    }
+
+    /// Execution history reports for every Stream in the instance.
+    ///
+    /// A Stream whose executions can't be listed gets a report with its `error` set,
+    /// the other Streams are still reported.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::history::ExecutionHistoryOptions;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use domo_pitchfork::util::csv::serialize_to_csv_str;
+    /// use std::time::Duration;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let options = ExecutionHistoryOptions::new(Duration::from_secs(7 * 24 * 60 * 60))
+    ///     .freshness(Duration::from_secs(24 * 60 * 60));
+    /// let reports = domo.streams().execution_history_all(&options)?;
+    /// for stale in reports.iter().filter(|r| r.stale) {
+    ///     println!("stream {} has no recent successful execution", stale.stream_id);
+    /// }
+    /// std::fs::write("stream_history.csv", serialize_to_csv_str(&reports, true)?)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn execution_history_all(
+        self,
+        options: &ExecutionHistoryOptions,
+    ) -> Result<Vec<ExecutionHistoryReport>, PitchforkError> {
+        const PAGE_SIZE: u32 = 500;
+        let domo = DomoPitchfork::with_token(self.auth);
+        let now = Utc::now();
+        let mut reports = Vec::new();
+        let mut offset = 0;
+        loop {
+            let streams = domo.streams().list(PAGE_SIZE, offset)?;
+            for stream in &streams {
+                // a Stream that fails is reported with its error instead of failing the report
+                let report = match domo.streams().all_executions(stream.id) {
+                    Ok(executions) => ExecutionHistoryReport::from_executions(
+                        stream.id,
+                        &executions,
+                        options,
+                        now,
+                    )?,
+                    Err(e) => ExecutionHistoryReport::from_error(stream.id, &e, options, now)?,
+                };
+                reports.push(report.with_dataset(stream));
+            }
+            if streams.len() < PAGE_SIZE as usize {
+                return Ok(reports);
+            }
+            offset += PAGE_SIZE;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::csv::serialize_to_csv_str;
+
+    fn execution(id: u32, started: &str, secs: i64, state: ExecutionState) -> StreamExecution {
+        let started_at = started.parse::<DateTime<Utc>>().unwrap();
+        StreamExecution {
+            id,
+            started_at,
+            ended_at: if state.is_terminal() {
+                Some(started_at + chrono::Duration::seconds(secs))
+            } else {
+                None
+            },
+            current_state: state,
+            created_at: None,
+            modified_at: None,
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&values, 50.0), Some(5.0));
+        assert_eq!(percentile(&values, 90.0), Some(9.0));
+        assert_eq!(percentile(&values, 99.0), Some(10.0));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_execution_history_report() {
+        let executions = vec![
+            execution(1, "2019-07-01T00:00:00Z", 30, ExecutionState::Success),
+            execution(2, "2019-07-09T00:00:00Z", 60, ExecutionState::Success),
+            execution(3, "2019-07-09T12:00:00Z", 120, ExecutionState::Success),
+            execution(4, "2019-07-09T18:00:00Z", 5, ExecutionState::Error),
+            execution(5, "2019-07-10T00:00:00Z", 0, ExecutionState::Active),
+        ];
+        let now = "2019-07-10T06:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let options = ExecutionHistoryOptions::new(2 * day).freshness(day);
+        let report =
+            ExecutionHistoryReport::from_executions(7, &executions, &options, now).unwrap();
+        assert_eq!(
+            (
+                report.executions,
+                report.successes,
+                report.failures,
+                report.active
+            ),
+            (4, 2, 1, 1)
+        );
+        assert_eq!(report.success_rate, Some(2.0 / 3.0));
+        assert_eq!(report.duration_p50_secs, Some(60.0));
+        assert_eq!(report.duration_max_secs, Some(120.0));
+        assert_eq!(report.last_success_execution_id, Some(3));
+        assert!(!report.stale);
+
+        let options = ExecutionHistoryOptions::new(2 * day).freshness(day / 4);
+        let report =
+            ExecutionHistoryReport::from_executions(7, &executions, &options, now).unwrap();
+        assert!(report.stale);
+
+        let csv = serialize_to_csv_str(&[report], true).unwrap();
+        assert!(csv.starts_with("stream_id,dataset_id,dataset_name,window_start"));
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().next().unwrap().ends_with(",stale,error"));
+    }
+
+    #[test]
+    fn test_execution_history_report_from_error() {
+        let now = "2019-07-10T06:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let options = ExecutionHistoryOptions::new(Duration::from_secs(60 * 60));
+        let e = PitchforkError::new("stream not found");
+        let report = ExecutionHistoryReport::from_error(7, &e, &options, now).unwrap();
+        assert_eq!(report.executions, 0);
+        assert_eq!(report.success_rate, None);
+        assert!(!report.stale);
+        assert_eq!(report.error.as_deref(), Some("stream not found"));
+    }
+}
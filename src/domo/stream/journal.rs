@@ -0,0 +1,181 @@
+//! On-disk journal that lets a [`StreamUploader`](super::uploader::StreamUploader) resume
+//! an interrupted upload into the same Stream Execution.
+use crate::error::PitchforkError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// State of a resumable Stream upload, saved as json after every part.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadJournal {
+    pub stream_id: u64,
+    pub execution_id: u32,
+    /// Part size the data was split with. A resumed upload reuses it so parts line up.
+    pub part_size: usize,
+    pub parts: Vec<JournalPart>,
+}
+
+/// A data part sent to the Stream Execution.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalPart {
+    pub part: u32,
+    /// Start of the part in the source: a byte offset for csv data, a record index for records.
+    pub source_start: u64,
+    /// End (exclusive) of the part in the source.
+    pub source_end: u64,
+    /// FNV-1a checksum of the part's csv.
+    pub checksum: u64,
+    pub rows: u64,
+    /// Whether Domo acknowledged the part upload.
+    pub acknowledged: bool,
+}
+
+impl UploadJournal {
+    pub fn new(stream_id: u64, execution_id: u32, part_size: usize) -> Self {
+        Self {
+            stream_id,
+            execution_id,
+            part_size,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Read a journal file. Returns `None` if the file doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, PitchforkError> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the journal to `path`, replacing the old file only once the new one is written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PitchforkError> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// The acknowledged entry for a part number.
+    pub fn acknowledged(&self, part: u32) -> Option<&JournalPart> {
+        self.parts.iter().find(|p| p.part == part && p.acknowledged)
+    }
+
+    fn record(&mut self, entry: JournalPart) {
+        match self.parts.iter_mut().find(|p| p.part == entry.part) {
+            Some(existing) => *existing = entry,
+            None => self.parts.push(entry),
+        }
+    }
+}
+
+/// A journal shared by the upload threads and saved on every change.
+pub(crate) struct JournalFile {
+    path: PathBuf,
+    journal: Mutex<UploadJournal>,
+}
+
+impl JournalFile {
+    pub(crate) fn new(path: PathBuf, journal: UploadJournal) -> Self {
+        Self {
+            path,
+            journal: Mutex::new(journal),
+        }
+    }
+
+    pub(crate) fn acknowledged_checksum(&self, part: u32) -> Option<u64> {
+        self.lock().acknowledged(part).map(|p| p.checksum)
+    }
+
+    /// Record a part that is about to be uploaded.
+    pub(crate) fn record(&self, entry: JournalPart) -> Result<(), PitchforkError> {
+        let mut journal = self.lock();
+        journal.record(entry);
+        journal.save(&self.path)
+    }
+
+    pub(crate) fn acknowledge(&self, part: u32) -> Result<(), PitchforkError> {
+        let mut journal = self.lock();
+        if let Some(entry) = journal.parts.iter_mut().find(|p| p.part == part) {
+            entry.acknowledged = true;
+        }
+        journal.save(&self.path)
+    }
+
+    pub(crate) fn save(&self) -> Result<(), PitchforkError> {
+        self.lock().save(&self.path)
+    }
+
+    /// Delete the journal file once the upload is committed or aborted.
+    pub(crate) fn remove(&self) -> Result<(), PitchforkError> {
+        remove_journal(&self.path)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, UploadJournal> {
+        self.journal.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub(crate) fn remove_journal(path: &Path) -> Result<(), PitchforkError> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() != io::ErrorKind::NotFound => Err(PitchforkError::new(format!(
+            "failed to remove upload journal {}: {}",
+            path.display(),
+            e
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 64 bit FNV-1a hash used to check a resumed part matches the part that was uploaded.
+pub fn checksum(data: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    data.iter().fold(OFFSET_BASIS, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(checksum(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(checksum(b"a,1\n"), checksum(b"a,2\n"));
+    }
+
+    #[test]
+    fn test_journal_roundtrip() {
+        let path = std::env::temp_dir().join(format!(
+            "pitchfork-journal-test-{}.json",
+            std::process::id()
+        ));
+        assert_eq!(UploadJournal::load(&path).unwrap(), None);
+        let file = JournalFile::new(path.clone(), UploadJournal::new(1, 2, 100));
+        let entry = JournalPart {
+            part: 1,
+            source_start: 0,
+            source_end: 4,
+            checksum: checksum(b"a,1\n"),
+            rows: 1,
+            acknowledged: false,
+        };
+        file.record(entry.clone()).unwrap();
+        assert_eq!(file.acknowledged_checksum(1), None);
+        file.acknowledge(1).unwrap();
+        assert_eq!(file.acknowledged_checksum(1), Some(entry.checksum));
+
+        let loaded = UploadJournal::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.execution_id, 2);
+        assert!(loaded.parts[0].acknowledged);
+        file.remove().unwrap();
+        assert_eq!(UploadJournal::load(&path).unwrap(), None);
+    }
+}
@@ -0,0 +1,455 @@
+//! Buffered sink that appends records to a Domo Stream as they arrive.
+//!
+//! A [`StreamSink`] serializes each record to a csv row and hands it to a background
+//! thread, which collects rows into data parts. A part is uploaded once it reaches the
+//! part size or has been buffering for longer than the max part age, and the Stream
+//! Execution is committed on [`flush`](StreamSink::flush), on drop, or every
+//! commit interval. The sink is meant for `APPEND` Streams, since every commit of a
+//! `REPLACE` Stream replaces its data.
+use super::uploader::{is_retryable, retry_backoff};
+use super::StreamExecution;
+use crate::error::PitchforkError;
+use crate::pitchfork::{DomoPitchfork, StreamsRequestBuilder};
+use crate::util::csv::serialize_to_csv_str;
+use log::{debug, warn};
+use serde::Serialize;
+use std::error::Error;
+use std::marker::PhantomData;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Thresholds of a [`StreamSink`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamSinkOptions {
+    /// Upload the buffered rows as a part once they reach this many bytes.
+    pub part_size: usize,
+    /// Upload the buffered rows once the oldest has waited this long.
+    pub max_part_age: Duration,
+    /// Commit the execution this long after it was created. `None` only commits on flush or drop.
+    pub commit_interval: Option<Duration>,
+    /// Number of rows that can wait for the background thread before `send` blocks.
+    pub buffer_rows: usize,
+    /// Number of times a failed request is retried.
+    pub max_retries: u32,
+    /// Delay before the first retry. The delay doubles with each retry.
+    pub retry_delay: Duration,
+}
+
+impl Default for StreamSinkOptions {
+    fn default() -> Self {
+        Self {
+            part_size: 10 * 1024 * 1024,
+            max_part_age: Duration::from_secs(30),
+            commit_interval: None,
+            buffer_rows: 10_000,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl StreamSinkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn part_size(mut self, bytes: usize) -> Self {
+        self.part_size = bytes.max(1);
+        self
+    }
+
+    pub fn max_part_age(mut self, age: Duration) -> Self {
+        self.max_part_age = age;
+        self
+    }
+
+    pub fn commit_interval(mut self, interval: Duration) -> Self {
+        self.commit_interval = Some(interval);
+        self
+    }
+
+    pub fn buffer_rows(mut self, rows: usize) -> Self {
+        self.buffer_rows = rows.max(1);
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+}
+
+impl<'t> StreamsRequestBuilder<'t, super::StreamDataset> {
+    /// Create a [`StreamSink`] that appends records to the given Stream.
+    ///
+    /// Creating a Stream Execution aborts any other execution on the Stream,
+    /// so a Stream should only have one sink writing to it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::sink::StreamSinkOptions;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use serde::Serialize;
+    /// use std::time::Duration;
+    /// #[derive(Serialize)]
+    /// struct Event {
+    ///     user: String,
+    ///     action: String,
+    /// }
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let options = StreamSinkOptions::new().commit_interval(Duration::from_secs(15 * 60));
+    /// let mut sink = domo.streams().sink(123, options);
+    /// sink.send(&Event { user: "a".into(), action: "login".into() })?;
+    /// sink.send(&Event { user: "b".into(), action: "logout".into() })?;
+    /// let execution = sink.close()?;
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn sink<T: Serialize>(self, stream_id: u64, options: StreamSinkOptions) -> StreamSink<T> {
+        StreamSink::new(self.auth.to_string(), stream_id, options)
+    }
+}
+
+enum Command {
+    Row(String),
+    /// Upload the buffered rows and commit, replying with the committed execution.
+    Commit(SyncSender<Result<Option<StreamExecution>, PitchforkError>>),
+}
+
+/// Appends `Serialize` records to a Domo Stream in the background.
+///
+/// If an upload or commit fails after its retries, the open execution is aborted and the
+/// sink stops: the rows of that execution and any rows still buffered are dropped, and the
+/// failing call and every later call return the error.
+pub struct StreamSink<T> {
+    tx: Option<SyncSender<Command>>,
+    worker: Option<JoinHandle<Result<(), PitchforkError>>>,
+    records: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize> StreamSink<T> {
+    fn new(auth: String, stream_id: u64, options: StreamSinkOptions) -> Self {
+        let (tx, rx) = mpsc::sync_channel(options.buffer_rows.max(1));
+        let worker = thread::spawn(move || SinkWorker::new(auth, stream_id, options).run(&rx));
+        Self {
+            tx: Some(tx),
+            worker: Some(worker),
+            records: PhantomData,
+        }
+    }
+
+    /// Buffer a record to be uploaded. Blocks while the buffer is full.
+    pub fn send(&mut self, record: &T) -> Result<(), PitchforkError> {
+        let row = serialize_to_csv_str(std::slice::from_ref(record), false)?;
+        self.command(Command::Row(row))
+    }
+
+    /// Upload the buffered records and commit the open execution.
+    /// Returns the committed execution, or `None` if nothing was sent since the last commit.
+    pub fn flush(&mut self) -> Result<Option<StreamExecution>, PitchforkError> {
+        let (reply_tx, reply_rx) = mpsc::sync_channel(1);
+        self.command(Command::Commit(reply_tx))?;
+        match reply_rx.recv() {
+            Ok(Err(e)) => {
+                // the worker has stopped, so later calls shouldn't queue rows for it
+                self.tx = None;
+                Err(e)
+            }
+            Ok(result) => result,
+            Err(_) => Err(self.stop()),
+        }
+    }
+
+    /// Flush the sink and stop its background thread.
+    pub fn close(mut self) -> Result<Option<StreamExecution>, PitchforkError> {
+        let result = self.flush();
+        self.stop();
+        result
+    }
+
+    fn command(&mut self, command: Command) -> Result<(), PitchforkError> {
+        let sent = match &self.tx {
+            Some(tx) => tx.send(command).is_ok(),
+            None => false,
+        };
+        if sent {
+            Ok(())
+        } else {
+            Err(self.stop())
+        }
+    }
+
+    /// Stop the background thread, returning the error it stopped with.
+    fn stop(&mut self) -> PitchforkError {
+        self.tx = None;
+        match self.worker.take().map(JoinHandle::join) {
+            Some(Ok(Err(e))) => e,
+            Some(Err(_)) => PitchforkError::new("stream sink thread panicked"),
+            _ => PitchforkError::new("stream sink is stopped"),
+        }
+    }
+}
+
+impl<T> Drop for StreamSink<T> {
+    /// Commits the buffered records, blocking until they're uploaded.
+    fn drop(&mut self) {
+        // disconnecting the channel tells the worker to commit and exit
+        self.tx = None;
+        if let Some(worker) = self.worker.take() {
+            match worker.join() {
+                Ok(Err(e)) => warn!("stream sink failed to commit on drop: {}", e),
+                Err(_) => warn!("stream sink thread panicked"),
+                Ok(Ok(())) => {}
+            }
+        }
+    }
+}
+
+/// The background thread of a sink, which owns the buffered part and the open execution.
+struct SinkWorker {
+    auth: String,
+    stream_id: u64,
+    options: StreamSinkOptions,
+    part: String,
+    part_rows: u64,
+    part_started: Option<Instant>,
+    /// Id and creation time of the open execution.
+    execution: Option<(u32, Instant)>,
+    part_num: u32,
+}
+
+impl SinkWorker {
+    fn new(auth: String, stream_id: u64, options: StreamSinkOptions) -> Self {
+        Self {
+            auth,
+            stream_id,
+            options,
+            part: String::new(),
+            part_rows: 0,
+            part_started: None,
+            execution: None,
+            part_num: 0,
+        }
+    }
+
+    fn run(mut self, rx: &Receiver<Command>) -> Result<(), PitchforkError> {
+        loop {
+            let command = match self.next_deadline() {
+                Some(deadline) => {
+                    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                        Ok(command) => Some(command),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match rx.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => break,
+                },
+            };
+            let result = match command {
+                Some(Command::Row(row)) => self.push(&row),
+                Some(Command::Commit(reply)) => match self.commit() {
+                    Ok(execution) => {
+                        let _ = reply.send(Ok(execution));
+                        continue;
+                    }
+                    Err(e) => {
+                        let e = self.fail(e);
+                        // the flush gets the error and later calls get a copy of it
+                        let stopped = copy_error(&e);
+                        let _ = reply.send(Err(e));
+                        return Err(stopped);
+                    }
+                },
+                None => self.on_deadline(),
+            };
+            if let Err(e) = result {
+                return Err(self.fail(e));
+            }
+        }
+        // the sink was dropped or closed
+        self.commit().map(|_| ()).map_err(|e| self.fail(e))
+    }
+
+    /// When the buffered part or the open execution is due.
+    fn next_deadline(&self) -> Option<Instant> {
+        let part = self.part_started.map(|t| t + self.options.max_part_age);
+        let commit = match (self.execution, self.options.commit_interval) {
+            (Some((_, created)), Some(interval)) => Some(created + interval),
+            _ => None,
+        };
+        match (part, commit) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn push(&mut self, row: &str) -> Result<(), PitchforkError> {
+        if self.part_rows == 0 {
+            self.part_started = Some(Instant::now());
+        }
+        self.part.push_str(row);
+        self.part_rows += 1;
+        if self.part.len() >= self.options.part_size {
+            self.upload_part()?;
+        }
+        Ok(())
+    }
+
+    fn on_deadline(&mut self) -> Result<(), PitchforkError> {
+        let now = Instant::now();
+        if self
+            .part_started
+            .map_or(false, |t| t + self.options.max_part_age <= now)
+        {
+            self.upload_part()?;
+        }
+        if let (Some((_, created)), Some(interval)) = (self.execution, self.options.commit_interval)
+        {
+            if created + interval <= now {
+                self.commit()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn upload_part(&mut self) -> Result<(), PitchforkError> {
+        if self.part_rows == 0 {
+            return Ok(());
+        }
+        let stream_id = self.stream_id;
+        let execution_id = match self.execution {
+            Some((id, _)) => id,
+            None => {
+                let execution =
+                    self.with_retries(|streams| streams.create_stream_execution(stream_id))?;
+                debug!(
+                    "stream sink opened execution {} on stream {}",
+                    execution.id, stream_id
+                );
+                self.execution = Some((execution.id, Instant::now()));
+                execution.id
+            }
+        };
+        let part_num = self.part_num + 1;
+        let part = &self.part;
+        self.with_retries(|streams| streams.upload_part(stream_id, execution_id, part_num, part))?;
+        self.part_num = part_num;
+        self.part.clear();
+        self.part_rows = 0;
+        self.part_started = None;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<Option<StreamExecution>, PitchforkError> {
+        self.upload_part()?;
+        let execution_id = match self.execution {
+            Some((id, _)) => id,
+            None => return Ok(None),
+        };
+        let stream_id = self.stream_id;
+        let execution =
+            self.with_retries(|streams| streams.commit_execution(stream_id, execution_id))?;
+        self.execution = None;
+        self.part_num = 0;
+        Ok(Some(execution))
+    }
+
+    /// Abort the open execution after an error so its parts aren't committed later.
+    fn fail(&mut self, e: PitchforkError) -> PitchforkError {
+        if let Some((execution_id, _)) = self.execution.take() {
+            if let Err(abort_err) = DomoPitchfork::with_token(&self.auth)
+                .streams()
+                .abort_stream_execution(self.stream_id, execution_id)
+            {
+                warn!(
+                    "failed to abort stream {} execution {}: {}",
+                    self.stream_id, execution_id, abort_err
+                );
+            }
+        }
+        e
+    }
+
+    fn with_retries<R, F>(&self, mut request: F) -> Result<R, PitchforkError>
+    where
+        F: FnMut(StreamsRequestBuilder<'_, super::StreamDataset>) -> Result<R, PitchforkError>,
+    {
+        let mut retries = 0;
+        loop {
+            match request(DomoPitchfork::with_token(&self.auth).streams()) {
+                Err(e) if retries < self.options.max_retries && is_retryable(&e) => {
+                    let delay = retry_backoff(self.options.retry_delay, retries);
+                    warn!(
+                        "retrying stream {} request in {:?}: {}",
+                        self.stream_id, delay, e
+                    );
+                    thread::sleep(delay);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A `PitchforkError` with the same kind and message, since errors can't be cloned.
+fn copy_error(e: &PitchforkError) -> PitchforkError {
+    let mut copy = match e.source() {
+        Some(source) => PitchforkError::new(source.to_string()),
+        None => e.kind.clone().into(),
+    };
+    copy.with_kind(e.kind.clone());
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PitchforkErrorKind;
+
+    #[test]
+    fn test_sink_deadlines() {
+        let options = StreamSinkOptions::new()
+            .max_part_age(Duration::from_secs(30))
+            .commit_interval(Duration::from_secs(10));
+        let mut worker = SinkWorker::new("token".to_string(), 1, options);
+        assert_eq!(worker.next_deadline(), None);
+
+        let now = Instant::now();
+        worker.part_started = Some(now);
+        assert_eq!(worker.next_deadline(), Some(now + Duration::from_secs(30)));
+        worker.execution = Some((1, now));
+        assert_eq!(worker.next_deadline(), Some(now + Duration::from_secs(10)));
+        worker.part_started = None;
+        assert_eq!(worker.next_deadline(), Some(now + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_sink_worker_buffers_rows() {
+        let options = StreamSinkOptions::new().part_size(1024);
+        let mut worker = SinkWorker::new("token".to_string(), 1, options);
+        worker.push("a,1\n").unwrap();
+        worker.push("b,2\n").unwrap();
+        assert_eq!(worker.part, "a,1\nb,2\n");
+        assert_eq!(worker.part_rows, 2);
+        assert!(worker.part_started.is_some());
+        assert!(worker.execution.is_none());
+    }
+
+    #[test]
+    fn test_copy_error() {
+        let e: PitchforkError =
+            PitchforkErrorKind::DomoBadRequest(400, "bad part".to_string()).into();
+        let copy = copy_error(&e.with_source(std::fmt::Error));
+        assert_eq!(copy.to_string(), "HTTP 400: bad part");
+        assert!(copy.source().is_some());
+    }
+}
@@ -0,0 +1,201 @@
+//! Split large csv files into Stream data parts by byte range.
+//!
+//! The file is scanned once to find row boundaries, honoring quoted values that contain
+//! line breaks, and each part can then be uploaded straight from the file with
+//! [`upload_part_from_file_range`](crate::pitchfork::StreamsRequestBuilder::upload_part_from_file_range)
+//! without reading it into memory.
+use crate::error::PitchforkError;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// A range of whole csv rows in a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CsvFilePart {
+    /// Byte offset of the first row.
+    pub start: u64,
+    /// Byte offset (exclusive) after the last row, including its line break.
+    pub end: u64,
+    /// Number of rows in the range, not counting blank lines.
+    pub rows: u64,
+}
+
+impl CsvFilePart {
+    /// Size of the part in bytes.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Split csv data into ranges of whole rows of at most `part_size` bytes. A single row
+/// bigger than `part_size` gets a part of its own. A line break inside a quoted value
+/// doesn't end a row. With `has_headers` the first row is left out of the parts, since
+/// Domo data parts must not include a header row.
+pub fn split_csv<R: Read>(
+    reader: R,
+    part_size: u64,
+    has_headers: bool,
+) -> Result<Vec<CsvFilePart>, PitchforkError> {
+    // close the part before the row starting at `row_start` if that row doesn't fit
+    let make_room = |parts: &mut Vec<CsvFilePart>, part: &mut CsvFilePart, row_start, end| {
+        if part.rows > 0 && end - part.start > part_size {
+            parts.push(CsvFilePart {
+                end: row_start,
+                ..*part
+            });
+            *part = CsvFilePart {
+                start: row_start,
+                end: row_start,
+                rows: 0,
+            };
+        }
+    };
+    let mut rdr = BufReader::new(reader);
+    let mut parts = Vec::new();
+    let mut skip_header = has_headers;
+    let mut in_quotes = false;
+    // offset of the next byte to scan
+    let mut offset: u64 = 0;
+    let mut row_start: u64 = 0;
+    let mut row_blank = true;
+    let mut part = CsvFilePart {
+        start: 0,
+        end: 0,
+        rows: 0,
+    };
+    loop {
+        let buf = rdr.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let len = buf.len();
+        for (i, b) in buf.iter().enumerate() {
+            match b {
+                b'"' => {
+                    in_quotes = !in_quotes;
+                    row_blank = false;
+                }
+                b'\n' if !in_quotes => {
+                    let end = offset + i as u64 + 1;
+                    if skip_header && !row_blank {
+                        skip_header = false;
+                        part.start = end;
+                    } else if !row_blank {
+                        make_room(&mut parts, &mut part, row_start, end);
+                        part.rows += 1;
+                    }
+                    part.end = end;
+                    if part.rows > 0 && part.len() >= part_size {
+                        parts.push(part);
+                        part = CsvFilePart {
+                            start: end,
+                            end,
+                            rows: 0,
+                        };
+                    }
+                    row_start = end;
+                    row_blank = true;
+                }
+                b'\r' => {}
+                _ => row_blank = false,
+            }
+        }
+        offset += len as u64;
+        rdr.consume(len);
+    }
+    if in_quotes {
+        return Err(PitchforkError::new(format!(
+            "csv row starting at byte {} has an unterminated quoted value",
+            row_start
+        )));
+    }
+    // a last row without a line break
+    if !row_blank && !skip_header {
+        make_room(&mut parts, &mut part, row_start, offset);
+        part.rows += 1;
+        part.end = offset;
+    }
+    if part.rows > 0 {
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+/// Split a csv file into ranges of whole rows. See [`split_csv`].
+///
+/// # Example
+/// ```no_run
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::domo::stream::split::split_csv_file;
+/// use domo_pitchfork::pitchfork::DomoPitchfork;
+/// let domo = DomoPitchfork::with_token("token");
+/// let parts = split_csv_file("orders.csv", 50 * 1024 * 1024, true)?;
+/// let execution = domo.streams().create_stream_execution(123)?;
+/// for (i, part) in parts.iter().enumerate() {
+///     domo.streams()
+///         .upload_part_from_file_range(123, execution.id, i as u32 + 1, "orders.csv", part)?;
+/// }
+/// domo.streams().commit_execution(123, execution.id)?;
+/// # Ok::<(), PitchforkError>(())
+/// ```
+pub fn split_csv_file<P: AsRef<Path>>(
+    path: P,
+    part_size: u64,
+    has_headers: bool,
+) -> Result<Vec<CsvFilePart>, PitchforkError> {
+    split_csv(File::open(path)?, part_size, has_headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slices<'a>(csv: &'a str, parts: &[CsvFilePart]) -> Vec<&'a str> {
+        parts
+            .iter()
+            .map(|p| &csv[p.start as usize..p.end as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_split_csv_rows() {
+        let csv = "name,note\na,\"multi\nline\"\nb,\"say \"\"hi\"\"\"\r\nc,3";
+        let parts = split_csv(csv.as_bytes(), 10, true).unwrap();
+        assert_eq!(
+            slices(csv, &parts),
+            vec!["a,\"multi\nline\"\n", "b,\"say \"\"hi\"\"\"\r\n", "c,3"]
+        );
+        assert_eq!(parts.iter().map(|p| p.rows).sum::<u64>(), 3);
+
+        let parts = split_csv(csv.as_bytes(), 1024, false).unwrap();
+        assert_eq!(slices(csv, &parts), vec![csv]);
+        assert_eq!(parts[0].rows, 4);
+    }
+
+    #[test]
+    fn test_split_csv_edges() {
+        assert!(split_csv("".as_bytes(), 10, false).unwrap().is_empty());
+        assert!(split_csv("a,b\n".as_bytes(), 10, true).unwrap().is_empty());
+        assert!(split_csv("a,b\n\n\n".as_bytes(), 1, true)
+            .unwrap()
+            .is_empty());
+        let parts = split_csv("a\n\nb\n".as_bytes(), 1, false).unwrap();
+        assert_eq!(parts.iter().map(|p| p.rows).collect::<Vec<_>>(), vec![1, 1]);
+        assert!(split_csv("a,\"open\n".as_bytes(), 10, false).is_err());
+    }
+
+    #[test]
+    fn test_split_csv_budget() {
+        let csv = "h\na,1\nb,2\nlong row,3\nc,4";
+        let parts = split_csv(csv.as_bytes(), 8, true).unwrap();
+        assert_eq!(
+            slices(csv, &parts),
+            vec!["a,1\nb,2\n", "long row,3\n", "c,4"]
+        );
+        assert!(parts.iter().all(|p| p.len() <= 8 || p.rows == 1));
+    }
+}
@@ -0,0 +1,1054 @@
+//! Upload data to a Domo Stream in parts with a single call.
+//!
+//! [`StreamUploader`] creates a Stream Execution, splits the data into csv parts on row
+//! boundaries, uploads the parts in parallel with retries and commits the execution.
+//! If anything fails the execution is aborted so no partial data is committed.
+//!
+//! With [`resumable`](StreamUploader::resumable) the upload is journaled to a local file
+//! instead, and a failed upload keeps its execution open so running it again skips the
+//! parts that were already uploaded.
+use super::journal::{checksum, remove_journal, JournalFile, JournalPart, UploadJournal};
+use super::split::{split_csv, CsvFilePart};
+use super::StreamExecution;
+use crate::error::{PitchforkError, PitchforkErrorKind};
+use crate::pitchfork::{DomoPitchfork, StreamsRequestBuilder};
+use crate::util::progress::{ProgressReporter, ProgressTracker};
+use log::{debug, warn};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Uploads data to a Domo Stream, managing the whole Stream Execution.
+///
+/// # Example
+/// ```no_run
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::pitchfork::DomoPitchfork;
+/// use std::fs::File;
+/// let domo = DomoPitchfork::with_token("token");
+/// let report = domo
+///     .streams()
+///     .uploader(123)
+///     .part_size(20 * 1024 * 1024)
+///     .parallelism(4)
+///     .upload_csv(File::open("orders.csv")?, true)?;
+/// println!("Uploaded {} rows in {} parts", report.rows, report.parts);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct StreamUploader<'t> {
+    auth: &'t str,
+    stream_id: u64,
+    part_size: usize,
+    parallelism: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    journal: Option<PathBuf>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+}
+
+impl<'t> fmt::Debug for StreamUploader<'t> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamUploader")
+            .field("auth", &"[REDACTED]")
+            .field("stream_id", &self.stream_id)
+            .field("part_size", &self.part_size)
+            .field("parallelism", &self.parallelism)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("journal", &self.journal)
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl<'t> StreamsRequestBuilder<'t, super::StreamDataset> {
+    /// Create a [`StreamUploader`] for the given Stream.
+    pub fn uploader(self, stream_id: u64) -> StreamUploader<'t> {
+        StreamUploader::new(self.auth, stream_id)
+    }
+}
+
+impl<'t> StreamUploader<'t> {
+    pub fn new(auth: &'t str, stream_id: u64) -> Self {
+        Self {
+            auth,
+            stream_id,
+            part_size: 10 * 1024 * 1024,
+            parallelism: 4,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+            journal: None,
+            progress: None,
+        }
+    }
+
+    /// Target size in bytes of each data part. Parts are split on row boundaries
+    /// so a part is only bigger than this if a single row is.
+    pub fn part_size(mut self, bytes: usize) -> Self {
+        self.part_size = bytes.max(1);
+        self
+    }
+
+    /// Max number of parts uploaded at the same time.
+    pub fn parallelism(mut self, threads: usize) -> Self {
+        self.parallelism = threads.max(1);
+        self
+    }
+
+    /// Number of times a failed part upload is retried.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Delay before the first retry of a part. The delay doubles with each retry.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Journal the upload to the file at `path` so it can be resumed.
+    ///
+    /// Parts are recorded with their source offsets and checksums as they're uploaded.
+    /// If the upload fails the execution is left open. Running the same upload again while
+    /// that execution is still active skips the acknowledged parts, as long as their
+    /// checksums still match, and commits it. The journal is deleted once the execution
+    /// is committed or aborted.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use std::fs::File;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let report = domo
+    ///     .streams()
+    ///     .uploader(123)
+    ///     .resumable("orders.journal.json")
+    ///     .upload_csv(File::open("orders.csv")?, true)?;
+    /// println!("Skipped {} parts uploaded by an earlier run", report.resumed_parts);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn resumable<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.journal = Some(path.into());
+        self
+    }
+
+    /// Report the upload's progress as parts complete. Parts skipped on resume count as completed.
+    ///
+    /// The total bytes and parts, and so the completed fraction and ETA, are only known for
+    /// [`upload_csv_file`](Self::upload_csv_file) and [`upload_csv_str`](Self::upload_csv_str).
+    pub fn progress(mut self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+
+    /// Upload csv data read from `reader`. A header row is skipped if `has_headers` is true.
+    pub fn upload_csv<R: Read>(
+        &self,
+        reader: R,
+        has_headers: bool,
+    ) -> Result<StreamUploadReport, StreamUploadError> {
+        self.upload_parts(|part_size| {
+            Ok(PartSource::new(CsvParts::new(
+                reader,
+                has_headers,
+                part_size,
+            )))
+        })
+    }
+
+    /// Upload the csv file at `path`. A header row is skipped if `has_headers` is true.
+    ///
+    /// The file is scanned for row boundaries first, so the number of parts is known
+    /// before the upload starts, and each part is read from the file as it's uploaded.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use domo_pitchfork::util::progress::LogProgress;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let report = domo
+    ///     .streams()
+    ///     .uploader(123)
+    ///     .progress(Arc::new(LogProgress::new("orders", Duration::from_secs(60))))
+    ///     .upload_csv_file("orders.csv", true)?;
+    /// println!("Uploaded {} rows in {} parts", report.rows, report.parts);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn upload_csv_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        has_headers: bool,
+    ) -> Result<StreamUploadReport, StreamUploadError> {
+        let path = path.as_ref();
+        self.upload_parts(|part_size| {
+            let ranges = split_csv(File::open(path)?, part_size as u64, has_headers)?;
+            Ok(PartSource::ranges(File::open(path)?, ranges))
+        })
+    }
+
+    /// Upload csv data held in memory. A header row is skipped if `has_headers` is true.
+    pub fn upload_csv_str(
+        &self,
+        csv: &str,
+        has_headers: bool,
+    ) -> Result<StreamUploadReport, StreamUploadError> {
+        self.upload_parts(|part_size| {
+            let ranges = split_csv(csv.as_bytes(), part_size as u64, has_headers)?;
+            Ok(PartSource::ranges(Cursor::new(csv.as_bytes()), ranges))
+        })
+    }
+
+    /// Upload `Serialize` records as csv rows.
+    pub fn upload_records<T, I>(&self, records: I) -> Result<StreamUploadReport, StreamUploadError>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        self.upload_parts(|part_size| {
+            Ok(PartSource::new(RecordParts::new(
+                records.into_iter(),
+                part_size,
+            )))
+        })
+    }
+
+    fn streams(&self) -> StreamsRequestBuilder<'t, super::StreamDataset> {
+        DomoPitchfork::with_token(self.auth).streams()
+    }
+
+    fn upload_parts<I, F>(&self, make_parts: F) -> Result<StreamUploadReport, StreamUploadError>
+    where
+        I: Iterator<Item = Result<CsvPart, PitchforkError>>,
+        F: FnOnce(usize) -> Result<PartSource<I>, PitchforkError>,
+    {
+        let start = Instant::now();
+        let resumed = match &self.journal {
+            Some(path) => self.active_journal(path)?,
+            None => None,
+        };
+        let part_size = resumed.as_ref().map_or(self.part_size, |j| j.part_size);
+        let PartSource {
+            mut parts,
+            total_bytes,
+            total_parts,
+        } = make_parts(part_size).map_err(|e| StreamUploadError::new(UploadStage::Split, e))?;
+        // read the first part before creating the execution so empty or unreadable
+        // data doesn't abort any execution already running on the stream.
+        let first = match parts.next() {
+            Some(Ok(part)) => part,
+            Some(Err(e)) => return Err(StreamUploadError::new(UploadStage::Split, e)),
+            None => {
+                return Err(StreamUploadError::new(
+                    UploadStage::Split,
+                    PitchforkError::new("data is empty"),
+                ))
+            }
+        };
+        let execution_id = match &resumed {
+            Some(journal) => journal.execution_id,
+            None => {
+                self.streams()
+                    .create_stream_execution(self.stream_id)
+                    .map_err(|e| StreamUploadError::new(UploadStage::CreateExecution, e))?
+                    .id
+            }
+        };
+        debug!(
+            "uploading to stream {} execution {}",
+            self.stream_id, execution_id
+        );
+        let journal = self.journal.as_ref().map(|path| {
+            let journal = resumed
+                .unwrap_or_else(|| UploadJournal::new(self.stream_id, execution_id, part_size));
+            JournalFile::new(path.clone(), journal)
+        });
+        if let Some(journal) = &journal {
+            if let Err(e) = journal.save() {
+                warn!("failed to write upload journal: {}", e);
+            }
+        }
+
+        let tracker = self
+            .progress
+            .as_ref()
+            .map(|reporter| ProgressTracker::new(Arc::clone(reporter), total_bytes, total_parts));
+        let (totals, failure) = self.upload_all(
+            execution_id,
+            std::iter::once(Ok(first)).chain(parts),
+            journal.as_ref(),
+            tracker.as_ref(),
+        );
+        let result = match failure {
+            Some(failure) => Err(failure),
+            None => self
+                .streams()
+                .commit_execution(self.stream_id, execution_id)
+                .map_err(|e| Failure::new(UploadStage::Commit, e)),
+        };
+        match result {
+            Ok(execution) => {
+                if let Some(journal) = &journal {
+                    if let Err(e) = journal.remove() {
+                        warn!("{}", e);
+                    }
+                }
+                if let Some(tracker) = &tracker {
+                    tracker.finish();
+                }
+                Ok(StreamUploadReport {
+                    stream_id: self.stream_id,
+                    execution,
+                    parts: totals.parts,
+                    resumed_parts: totals.resumed_parts,
+                    rows: totals.rows,
+                    bytes: totals.bytes,
+                    retries: totals.retries,
+                    elapsed: start.elapsed(),
+                })
+            }
+            Err(failure) => {
+                // a journaled upload keeps its execution open to be resumed,
+                // unless its uploaded parts no longer match the source.
+                let abort = journal.is_none() || failure.discard_execution;
+                let aborted = abort && self.abort(execution_id);
+                if aborted {
+                    if let Some(journal) = &journal {
+                        if let Err(e) = journal.remove() {
+                            warn!("{}", e);
+                        }
+                    }
+                }
+                Err(StreamUploadError {
+                    stage: failure.stage,
+                    execution_id: Some(execution_id),
+                    aborted,
+                    parts_uploaded: totals.parts,
+                    source: failure.source,
+                })
+            }
+        }
+    }
+
+    fn abort(&self, execution_id: u32) -> bool {
+        match self
+            .streams()
+            .abort_stream_execution(self.stream_id, execution_id)
+        {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(
+                    "failed to abort stream {} execution {}: {}",
+                    self.stream_id, execution_id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Load the journal at `path` if its execution can still be resumed.
+    /// A journal for an execution that is no longer active is deleted.
+    fn active_journal(&self, path: &Path) -> Result<Option<UploadJournal>, StreamUploadError> {
+        let journal = match UploadJournal::load(path) {
+            Ok(Some(journal)) => journal,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(StreamUploadError::new(UploadStage::Journal, e)),
+        };
+        if journal.stream_id != self.stream_id {
+            return Err(StreamUploadError::new(
+                UploadStage::Journal,
+                PitchforkError::new(format!(
+                    "upload journal {} is for stream {}, not stream {}",
+                    path.display(),
+                    journal.stream_id,
+                    self.stream_id
+                )),
+            ));
+        }
+        let active = match self
+            .streams()
+            .execution_info(self.stream_id, journal.execution_id)
+        {
+            Ok(execution) => execution.is_active(),
+            Err(PitchforkError {
+                kind: PitchforkErrorKind::DomoBadRequest(404, _),
+                ..
+            }) => false,
+            Err(e) => return Err(StreamUploadError::new(UploadStage::Journal, e)),
+        };
+        if active {
+            debug!(
+                "resuming stream {} execution {} from {}",
+                self.stream_id,
+                journal.execution_id,
+                path.display()
+            );
+            Ok(Some(journal))
+        } else {
+            remove_journal(path).map_err(|e| StreamUploadError::new(UploadStage::Journal, e))?;
+            Ok(None)
+        }
+    }
+
+    /// Upload all parts with `parallelism` worker threads. Returns the totals of the
+    /// uploaded parts and the first failure, if any.
+    fn upload_all<I>(
+        &self,
+        execution_id: u32,
+        parts: I,
+        journal: Option<&JournalFile>,
+        tracker: Option<&ProgressTracker>,
+    ) -> (PartTotals, Option<Failure>)
+    where
+        I: Iterator<Item = Result<CsvPart, PitchforkError>>,
+    {
+        // the bounded channel keeps at most `parallelism` parts in memory waiting for a worker
+        let (tx, rx) = mpsc::sync_channel::<(u32, CsvPart)>(self.parallelism);
+        let rx = Mutex::new(rx);
+        let failed = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            let workers: Vec<_> = (0..self.parallelism)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut totals = PartTotals::default();
+                        let mut failure = None;
+                        loop {
+                            let next = rx.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                            let (part_num, part) = match next {
+                                Ok(p) => p,
+                                Err(_) => break,
+                            };
+                            // keep draining after a failure so the producer never blocks
+                            if failed.load(Ordering::SeqCst) {
+                                continue;
+                            }
+                            match self.upload_with_retries(execution_id, part_num, &part.csv) {
+                                Ok(retries) => {
+                                    totals.add(&part, retries);
+                                    if let Some(tracker) = tracker {
+                                        tracker.add_bytes(part.csv.len() as u64);
+                                        tracker.part_done(part.rows);
+                                    }
+                                    if let Some(journal) = journal {
+                                        if let Err(e) = journal.acknowledge(part_num) {
+                                            warn!("failed to write upload journal: {}", e);
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    failed.store(true, Ordering::SeqCst);
+                                    failure =
+                                        Some(Failure::new(UploadStage::UploadPart(part_num), e));
+                                }
+                            }
+                        }
+                        (totals, failure)
+                    })
+                })
+                .collect();
+
+            let mut failure = None;
+            let mut resumed_parts = 0;
+            let mut part_num: u32 = 0;
+            for part in parts {
+                if failed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let part = match part {
+                    Ok(part) => part,
+                    Err(e) => {
+                        failed.store(true, Ordering::SeqCst);
+                        failure = Some(Failure::new(UploadStage::Split, e));
+                        break;
+                    }
+                };
+                part_num += 1;
+                if let Some(journal) = journal {
+                    match journal_part(journal, part_num, &part) {
+                        Ok(true) => {
+                            resumed_parts += 1;
+                            if let Some(tracker) = tracker {
+                                tracker.add_bytes(part.csv.len() as u64);
+                                tracker.part_done(part.rows);
+                            }
+                            continue;
+                        }
+                        Ok(false) => {}
+                        Err(f) => {
+                            failed.store(true, Ordering::SeqCst);
+                            failure = Some(f);
+                            break;
+                        }
+                    }
+                }
+                if tx.send((part_num, part)).is_err() {
+                    break;
+                }
+            }
+            drop(tx);
+
+            let mut totals = PartTotals {
+                resumed_parts,
+                ..PartTotals::default()
+            };
+            for worker in workers {
+                let (worker_totals, worker_failure) =
+                    worker.join().unwrap_or_else(|e| panic::resume_unwind(e));
+                totals.merge(&worker_totals);
+                if failure.is_none() {
+                    failure = worker_failure;
+                }
+            }
+            (totals, failure)
+        })
+    }
+
+    /// Upload a part, retrying failures that might succeed on another attempt.
+    /// Returns the number of retries it took.
+    fn upload_with_retries(
+        &self,
+        execution_id: u32,
+        part_num: u32,
+        csv: &str,
+    ) -> Result<u32, PitchforkError> {
+        let mut retries = 0;
+        loop {
+            match self
+                .streams()
+                .upload_part(self.stream_id, execution_id, part_num, csv)
+            {
+                Ok(_) => return Ok(retries),
+                Err(e) if retries < self.max_retries && is_retryable(&e) => {
+                    let delay = retry_backoff(self.retry_delay, retries);
+                    warn!(
+                        "retrying part {} of stream {} execution {} in {:?}: {}",
+                        part_num, self.stream_id, execution_id, delay, e
+                    );
+                    thread::sleep(delay);
+                    retries += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Check a part against the journal of a resumed upload and record it if it needs uploading.
+/// Returns whether the part was already uploaded.
+fn journal_part(journal: &JournalFile, part_num: u32, part: &CsvPart) -> Result<bool, Failure> {
+    let part_checksum = checksum(part.csv.as_bytes());
+    match journal.acknowledged_checksum(part_num) {
+        Some(c) if c == part_checksum => return Ok(true),
+        Some(_) => {
+            return Err(Failure {
+                stage: UploadStage::Split,
+                source: PitchforkError::new(format!(
+                    "part {} changed since it was uploaded, the data can't be resumed",
+                    part_num
+                )),
+                discard_execution: true,
+            })
+        }
+        None => {}
+    }
+    let entry = JournalPart {
+        part: part_num,
+        source_start: part.source_start,
+        source_end: part.source_end,
+        checksum: part_checksum,
+        rows: part.rows,
+        acknowledged: false,
+    };
+    if let Err(e) = journal.record(entry) {
+        warn!("failed to write upload journal: {}", e);
+    }
+    Ok(false)
+}
+
+/// Network errors, rate limiting and server errors are worth retrying.
+/// Other Domo errors like a bad request won't succeed on another attempt.
+pub(crate) fn is_retryable(e: &PitchforkError) -> bool {
+    match e.kind {
+        PitchforkErrorKind::Reqwest | PitchforkErrorKind::Io => true,
+        PitchforkErrorKind::DomoBadRequest(status, _) => status == 429 || status >= 500,
+        _ => false,
+    }
+}
+
+/// Longest delay between two retries, unless the first delay is already longer.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Delay before retry number `retries + 1`, doubling from `first` up to `MAX_RETRY_DELAY`.
+pub(crate) fn retry_backoff(first: Duration, retries: u32) -> Duration {
+    first
+        .checked_mul(2_u32.saturating_pow(retries))
+        .unwrap_or(Duration::MAX)
+        .min(MAX_RETRY_DELAY.max(first))
+}
+
+/// Step of a Stream upload that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadStage {
+    /// Reading or splitting the data into parts.
+    Split,
+    CreateExecution,
+    /// Uploading the data part with this part number.
+    UploadPart(u32),
+    Commit,
+    /// Reading, checking or writing the upload journal of a resumable upload.
+    Journal,
+}
+
+impl fmt::Display for UploadStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadStage::Split => write!(f, "splitting data into parts"),
+            UploadStage::CreateExecution => write!(f, "creating stream execution"),
+            UploadStage::UploadPart(part) => write!(f, "uploading part {}", part),
+            UploadStage::Commit => write!(f, "committing stream execution"),
+            UploadStage::Journal => write!(f, "resuming from the upload journal"),
+        }
+    }
+}
+
+/// Result of a successful [`StreamUploader`] upload.
+#[derive(Clone, Debug)]
+pub struct StreamUploadReport {
+    pub stream_id: u64,
+    /// The committed Stream Execution.
+    pub execution: StreamExecution,
+    /// Number of parts uploaded by this run.
+    pub parts: u32,
+    /// Number of parts skipped because an earlier run of a resumable upload uploaded them.
+    pub resumed_parts: u32,
+    /// Rows and bytes uploaded by this run.
+    pub rows: u64,
+    pub bytes: u64,
+    /// Total number of part upload retries.
+    pub retries: u32,
+    pub elapsed: Duration,
+}
+
+/// A failed [`StreamUploader`] upload.
+#[derive(Debug)]
+pub struct StreamUploadError {
+    pub stage: UploadStage,
+    /// The execution that was created, if the upload got that far.
+    pub execution_id: Option<u32>,
+    /// Whether the execution was aborted after the failure.
+    pub aborted: bool,
+    /// Number of parts that were uploaded before the upload failed.
+    pub parts_uploaded: u32,
+    pub source: PitchforkError,
+}
+
+impl StreamUploadError {
+    fn new(stage: UploadStage, source: PitchforkError) -> Self {
+        Self {
+            stage,
+            execution_id: None,
+            aborted: false,
+            parts_uploaded: 0,
+            source,
+        }
+    }
+}
+
+impl fmt::Display for StreamUploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stream upload failed {}: {}", self.stage, self.source)?;
+        if let Some(id) = self.execution_id {
+            let state = if self.aborted {
+                "aborted"
+            } else {
+                "could not be aborted"
+            };
+            write!(f, " (execution {} {})", id, state)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for StreamUploadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// The failed stage of an upload in progress.
+struct Failure {
+    stage: UploadStage,
+    source: PitchforkError,
+    /// Abort the execution even if the upload is resumable.
+    discard_execution: bool,
+}
+
+impl Failure {
+    fn new(stage: UploadStage, source: PitchforkError) -> Self {
+        Self {
+            stage,
+            source,
+            discard_execution: false,
+        }
+    }
+}
+
+impl From<StreamUploadError> for PitchforkError {
+    fn from(e: StreamUploadError) -> Self {
+        PitchforkError::new(e)
+    }
+}
+
+/// A csv data part without a header row.
+pub(crate) struct CsvPart {
+    pub(crate) csv: String,
+    pub(crate) rows: u64,
+    /// Range of the part in the source, see [`JournalPart`].
+    pub(crate) source_start: u64,
+    pub(crate) source_end: u64,
+}
+
+/// The parts of an upload, with their totals if they're known before the upload.
+struct PartSource<I> {
+    parts: I,
+    total_bytes: Option<u64>,
+    total_parts: Option<u32>,
+}
+
+impl<I> PartSource<I> {
+    fn new(parts: I) -> Self {
+        Self {
+            parts,
+            total_bytes: None,
+            total_parts: None,
+        }
+    }
+}
+
+impl<R: Read + Seek> PartSource<RangeParts<R>> {
+    fn ranges(source: R, ranges: Vec<CsvFilePart>) -> Self {
+        Self {
+            total_bytes: Some(ranges.iter().map(CsvFilePart::len).sum()),
+            total_parts: Some(ranges.len() as u32),
+            parts: RangeParts {
+                source,
+                ranges: ranges.into_iter(),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct PartTotals {
+    parts: u32,
+    resumed_parts: u32,
+    rows: u64,
+    bytes: u64,
+    retries: u32,
+}
+
+impl PartTotals {
+    fn add(&mut self, part: &CsvPart, retries: u32) {
+        self.parts += 1;
+        self.rows += part.rows;
+        self.bytes += part.csv.len() as u64;
+        self.retries += retries;
+    }
+
+    fn merge(&mut self, other: &PartTotals) {
+        self.parts += other.parts;
+        self.resumed_parts += other.resumed_parts;
+        self.rows += other.rows;
+        self.bytes += other.bytes;
+        self.retries += other.retries;
+    }
+}
+
+/// Collects csv rows until a part reaches its target size.
+struct PartBuilder {
+    wtr: csv::Writer<Vec<u8>>,
+    rows: u64,
+    part_size: usize,
+    source_start: u64,
+    source_end: u64,
+}
+
+impl PartBuilder {
+    fn new(part_size: usize) -> Self {
+        Self {
+            wtr: Self::writer(),
+            rows: 0,
+            part_size,
+            source_start: 0,
+            source_end: 0,
+        }
+    }
+
+    fn writer() -> csv::Writer<Vec<u8>> {
+        csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(Vec::new())
+    }
+
+    /// Whether the part has reached its target size after the last row,
+    /// which spans `start..end` in the source.
+    fn row_written(&mut self, start: u64, end: u64) -> Result<bool, PitchforkError> {
+        if self.rows == 0 {
+            self.source_start = start;
+        }
+        self.source_end = end;
+        self.rows += 1;
+        self.wtr.flush()?;
+        Ok(self.wtr.get_ref().len() >= self.part_size)
+    }
+
+    fn take(&mut self) -> Result<Option<CsvPart>, PitchforkError> {
+        if self.rows == 0 {
+            return Ok(None);
+        }
+        let wtr = std::mem::replace(&mut self.wtr, Self::writer());
+        let bytes = wtr.into_inner().map_err(|e| {
+            let mut err = PitchforkError::new(e.to_string());
+            err.with_kind(PitchforkErrorKind::Csv);
+            err
+        })?;
+        let rows = std::mem::replace(&mut self.rows, 0);
+        let csv = String::from_utf8(bytes).map_err(PitchforkError::new)?;
+        Ok(Some(CsvPart {
+            csv,
+            rows,
+            source_start: self.source_start,
+            source_end: self.source_end,
+        }))
+    }
+}
+
+/// Splits csv data from a reader into parts.
+pub(crate) struct CsvParts<R: Read> {
+    rdr: csv::Reader<R>,
+    record: csv::StringRecord,
+    builder: PartBuilder,
+    done: bool,
+}
+
+impl<R: Read> CsvParts<R> {
+    pub(crate) fn new(reader: R, has_headers: bool, part_size: usize) -> Self {
+        let rdr = csv::ReaderBuilder::new()
+            .has_headers(has_headers)
+            .flexible(true)
+            .from_reader(reader);
+        Self {
+            rdr,
+            record: csv::StringRecord::new(),
+            builder: PartBuilder::new(part_size),
+            done: false,
+        }
+    }
+
+    fn next_part(&mut self) -> Result<Option<CsvPart>, PitchforkError> {
+        while self.rdr.read_record(&mut self.record)? {
+            self.builder.wtr.write_record(&self.record)?;
+            let start = self.record.position().map_or(0, csv::Position::byte);
+            let end = self.rdr.position().byte();
+            if self.builder.row_written(start, end)? {
+                return self.builder.take();
+            }
+        }
+        self.done = true;
+        self.builder.take()
+    }
+}
+
+impl<R: Read> Iterator for CsvParts<R> {
+    type Item = Result<CsvPart, PitchforkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let part = self.next_part();
+        if part.is_err() {
+            self.done = true;
+        }
+        part.transpose()
+    }
+}
+
+/// Reads csv parts from byte ranges of a source found by [`split_csv`].
+struct RangeParts<R> {
+    source: R,
+    ranges: std::vec::IntoIter<CsvFilePart>,
+}
+
+impl<R: Read + Seek> RangeParts<R> {
+    fn read_part(&mut self, range: CsvFilePart) -> Result<CsvPart, PitchforkError> {
+        self.source.seek(SeekFrom::Start(range.start))?;
+        let mut buf = Vec::with_capacity(range.len() as usize);
+        (&mut self.source).take(range.len()).read_to_end(&mut buf)?;
+        if buf.len() as u64 != range.len() {
+            return Err(PitchforkError::new(format!(
+                "csv data changed while uploading, expected {} bytes at offset {}",
+                range.len(),
+                range.start
+            )));
+        }
+        Ok(CsvPart {
+            csv: String::from_utf8(buf).map_err(PitchforkError::new)?,
+            rows: range.rows,
+            source_start: range.start,
+            source_end: range.end,
+        })
+    }
+}
+
+impl<R: Read + Seek> Iterator for RangeParts<R> {
+    type Item = Result<CsvPart, PitchforkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = self.ranges.next()?;
+        Some(self.read_part(range))
+    }
+}
+
+/// Serializes records into csv parts.
+pub(crate) struct RecordParts<I> {
+    records: I,
+    builder: PartBuilder,
+    index: u64,
+    done: bool,
+}
+
+impl<I> RecordParts<I> {
+    pub(crate) fn new(records: I, part_size: usize) -> Self {
+        Self {
+            records,
+            builder: PartBuilder::new(part_size),
+            index: 0,
+            done: false,
+        }
+    }
+}
+
+impl<T: Serialize, I: Iterator<Item = T>> RecordParts<I> {
+    fn next_part(&mut self) -> Result<Option<CsvPart>, PitchforkError> {
+        for record in self.records.by_ref() {
+            self.builder.wtr.serialize(record)?;
+            self.index += 1;
+            if self.builder.row_written(self.index - 1, self.index)? {
+                return self.builder.take();
+            }
+        }
+        self.done = true;
+        self.builder.take()
+    }
+}
+
+impl<T: Serialize, I: Iterator<Item = T>> Iterator for RecordParts<I> {
+    type Item = Result<CsvPart, PitchforkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let part = self.next_part();
+        if part.is_err() {
+            self.done = true;
+        }
+        part.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_parts_split_on_rows() {
+        let csv = "name,note\na,\"multi\nline\"\nb,2\nc,3\n";
+        let parts: Vec<CsvPart> = CsvParts::new(csv.as_bytes(), true, 10)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let csvs: Vec<&str> = parts.iter().map(|p| p.csv.as_str()).collect();
+        assert_eq!(csvs, vec!["a,\"multi\nline\"\n", "b,2\nc,3\n"]);
+        assert_eq!(parts.iter().map(|p| p.rows).sum::<u64>(), 3);
+        let ranges: Vec<(u64, u64)> = parts
+            .iter()
+            .map(|p| (p.source_start, p.source_end))
+            .collect();
+        assert_eq!(ranges, vec![(10, 25), (25, 33)]);
+    }
+
+    #[test]
+    fn test_record_parts() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            name: &'static str,
+        }
+        let rows = (0..5).map(|id| Row { id, name: "x" });
+        let parts: Vec<CsvPart> = RecordParts::new(rows, 8).collect::<Result<_, _>>().unwrap();
+        let csvs: Vec<&str> = parts.iter().map(|p| p.csv.as_str()).collect();
+        assert_eq!(csvs, vec!["0,x\n1,x\n", "2,x\n3,x\n", "4,x\n"]);
+        assert_eq!((parts[1].source_start, parts[1].source_end), (2, 4));
+        assert!(RecordParts::new(Vec::<Row>::new().into_iter(), 8)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_range_parts_totals() {
+        let csv = "id,name\n1,a\n2,b\n3,c\n";
+        let ranges = split_csv(csv.as_bytes(), 8, true).unwrap();
+        let source = PartSource::ranges(Cursor::new(csv.as_bytes()), ranges);
+        assert_eq!(
+            (source.total_bytes, source.total_parts),
+            (Some(12), Some(2))
+        );
+        let parts: Vec<CsvPart> = source.parts.collect::<Result<_, _>>().unwrap();
+        let csvs: Vec<&str> = parts.iter().map(|p| p.csv.as_str()).collect();
+        assert_eq!(csvs, vec!["1,a\n2,b\n", "3,c\n"]);
+        assert_eq!((parts[1].source_start, parts[1].source_end), (16, 20));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let err = |status| -> PitchforkError {
+            PitchforkErrorKind::DomoBadRequest(status, String::new()).into()
+        };
+        assert!(is_retryable(&err(503)));
+        assert!(is_retryable(&err(429)));
+        assert!(!is_retryable(&err(400)));
+        assert!(!is_retryable(&PitchforkError::new("data is empty")));
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let second = Duration::from_secs(1);
+        assert_eq!(retry_backoff(second, 0), second);
+        assert_eq!(retry_backoff(second, 3), 8 * second);
+        assert_eq!(retry_backoff(second, 40), MAX_RETRY_DELAY);
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(retry_backoff(hour, 2), hour);
+        assert_eq!(retry_backoff(Duration::MAX, 1), Duration::MAX);
+    }
+
+    #[test]
+    fn test_debug_redacts_token() {
+        let uploader = StreamUploader::new("SECRET-TOKEN-123", 7);
+        let debug = format!("{:?}", uploader);
+        assert!(!debug.contains("SECRET-TOKEN-123"));
+        assert!(debug.contains("stream_id: 7"));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::domo::dataset::SchemaDiff;
+use crate::domo::stream::UpdateMethod;
+use crate::util::validate::ValidationReport;
 use std::error::Error;
 use std::fmt;
 use std::io;
@@ -21,6 +24,16 @@ pub enum PitchforkErrorKind {
     DomoBadRequest(u16, String),
     /// Io Error.
     Io,
+    /// More than one Domo object matched a name lookup. Holds the name and the ids of all candidates.
+    AmbiguousName(String, Vec<String>),
+    /// Data failed a pre-upload check against a Dataset schema.
+    Validation(ValidationReport),
+    /// A schema migration was refused because it would remove, retype or reorder columns.
+    /// Holds the Dataset id and the refused changes.
+    DestructiveSchemaChange(String, Box<SchemaDiff>),
+    /// A Stream uses a different update method than the one requested.
+    /// Holds the Stream id, its update method and the requested one.
+    UpdateMethodMismatch(u64, UpdateMethod, UpdateMethod),
     Unknown,
 }
 
@@ -72,6 +85,26 @@ impl fmt::Display for PitchforkError {
             PitchforkErrorKind::Csv => write!(f, "Csv Error in domo_pitchfork"),
             PitchforkErrorKind::Serde => write!(f, "Serde Error in domo_pitchfork"),
             PitchforkErrorKind::DomoBadRequest(status_code, response_body) => write!(f, "HTTP {}: {}", status_code, response_body),
+            PitchforkErrorKind::AmbiguousName(name, candidates) => write!(
+                f,
+                "{} matches found for name '{}': {}",
+                candidates.len(),
+                name,
+                candidates.join(", ")
+            ),
+            PitchforkErrorKind::Validation(report) => {
+                write!(f, "Data failed schema validation: {}", report)
+            }
+            PitchforkErrorKind::DestructiveSchemaChange(dataset_id, diff) => write!(
+                f,
+                "refusing destructive schema changes to dataset {}: {}",
+                dataset_id, diff
+            ),
+            PitchforkErrorKind::UpdateMethodMismatch(stream_id, current, requested) => write!(
+                f,
+                "stream {} uses update method {}, not {}",
+                stream_id, current, requested
+            ),
             PitchforkErrorKind::Unknown => write!(f, "Unknown Pitchfork Error"),
             PitchforkErrorKind::Io => write!(f, "io::Error"),
         }
@@ -129,6 +162,15 @@ impl From<serde_json::Error> for PitchforkError {
     }
 }
 
+impl From<ValidationReport> for PitchforkError {
+    fn from(report: ValidationReport) -> Self {
+        Self {
+            kind: PitchforkErrorKind::Validation(report),
+            source: None,
+        }
+    }
+}
+
 impl From<()> for PitchforkError {
     fn from(_: ()) -> Self {
         Self {
@@ -1,14 +1,16 @@
+use crate::auth::{AuditScope, DashboardScope, DataScope, ScopedToken, Unscoped, UserScope};
 use crate::domo::activity_log::ActivityLogEntry;
 use crate::domo::dataset::Dataset;
 use crate::domo::group::GroupInfo;
 use crate::domo::page::PageInfo;
-use crate::domo::stream::StreamDataset;
+use crate::domo::stream::{PublishedStream, StreamDataset, UpdateMethod};
 use crate::domo::user::User;
 use crate::error::{PitchforkError, PitchforkErrorKind};
 use lazy_static::lazy_static;
 use reqwest::Client;
 use reqwest::Method;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::marker::PhantomData;
 
 macro_rules! impl_domo_requests {
@@ -54,17 +56,55 @@ lazy_static! {
 }
 
 /// `DomoPitchfork` is the top-level object to use to interact with the various Domo APIs
-#[derive(Clone)]
-pub struct DomoPitchfork<'t> {
+///
+/// `S` tracks the scopes of the token at the type level. Clients created with
+/// [`with_token`](DomoPitchfork::with_token) are [`Unscoped`] and expose every API,
+/// clients created with [`with_scoped_token`](DomoPitchfork::with_scoped_token) only expose
+/// the APIs their token has scope for.
+pub struct DomoPitchfork<'t, S = Unscoped> {
     /// Domo Auth Token
     auth: &'t str,
+    scopes: PhantomData<fn() -> S>,
+}
+
+impl<'t, S> Clone for DomoPitchfork<'t, S> {
+    fn clone(&self) -> Self {
+        Self {
+            auth: self.auth,
+            scopes: PhantomData,
+        }
+    }
 }
 
 impl<'t> DomoPitchfork<'t> {
     /// Create a new DomoPitchfork with a Domo Auth token
     pub fn with_token(token: &'t str) -> Self {
-        Self { auth: token }
+        Self {
+            auth: token,
+            scopes: PhantomData,
+        }
+    }
+}
+
+impl<'t, S> DomoPitchfork<'t, S> {
+    /// Create a new DomoPitchfork that only exposes the APIs the token has scope for.
+    pub fn with_scoped_token(token: &'t ScopedToken<S>) -> Self {
+        Self {
+            auth: token.as_str(),
+            scopes: PhantomData,
+        }
     }
+    /// Interact with Domo Projects API
+    pub fn projects(&self) -> ProjectsRequestBuilder<'t, ()> {
+        DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/projects/").into()
+    }
+    /// Interact with Domo Accounts API
+    pub fn accounts(&self) -> AccountsRequestBuilder<'t, ()> {
+        DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/accounts/").into()
+    }
+}
+
+impl<'t, S: DataScope> DomoPitchfork<'t, S> {
     /// Interact with Domo Datasets API
     pub fn datasets(&self) -> DatasetsRequestBuilder<'t, Dataset> {
         DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/datasets/").into()
@@ -73,6 +113,36 @@ impl<'t> DomoPitchfork<'t> {
     pub fn streams(&self) -> StreamsRequestBuilder<'t, StreamDataset> {
         DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/streams/").into()
     }
+    /// Publish records to the Stream whose Dataset is named `dataset_name`, creating the
+    /// Stream if it doesn't exist. See [`StreamsRequestBuilder::publish`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use domo_pitchfork::error::PitchforkError;
+    /// use domo_pitchfork::domo::stream::UpdateMethod;
+    /// use domo_pitchfork::pitchfork::DomoPitchfork;
+    /// use serde::Serialize;
+    /// #[derive(Serialize)]
+    /// struct Release {
+    ///     name: &'static str,
+    ///     version: &'static str,
+    /// }
+    /// let domo = DomoPitchfork::with_token("token");
+    /// let release = Release { name: "pitchfork", version: "0.4.0" };
+    /// domo.publish("Crate Releases", &[release], UpdateMethod::Append)?;
+    /// # Ok::<(), PitchforkError>(())
+    /// ```
+    pub fn publish<T: Serialize>(
+        &self,
+        dataset_name: &str,
+        records: &[T],
+        update_method: UpdateMethod,
+    ) -> Result<PublishedStream, PitchforkError> {
+        self.streams().publish(dataset_name, records, update_method)
+    }
+}
+
+impl<'t, S: UserScope> DomoPitchfork<'t, S> {
     /// Interact with Domo Users API
     pub fn users(&self) -> UsersRequestBuilder<'t, User> {
         DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/users/").into()
@@ -81,22 +151,20 @@ impl<'t> DomoPitchfork<'t> {
     pub fn groups(&self) -> GroupsRequestBuilder<'t, GroupInfo> {
         DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/groups/").into()
     }
+}
+
+impl<'t, S: DashboardScope> DomoPitchfork<'t, S> {
     /// Interact with Domo Pages API
     pub fn pages(&self) -> PagesRequestBuilder<'t, PageInfo> {
         DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/pages/").into()
     }
+}
+
+impl<'t, S: AuditScope> DomoPitchfork<'t, S> {
     /// Interact with Domo Activity Log API.
     pub fn audit(&self) -> ActivitiesRequestBuilder<'t, ActivityLogEntry> {
         DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/audit/").into()
     }
-    /// Interact with Domo Projects API
-    pub fn projects(&self) -> ProjectsRequestBuilder<'t, ()> {
-        DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/projects/").into()
-    }
-    /// Interact with Domo Accounts API
-    pub fn accounts(&self) -> AccountsRequestBuilder<'t, ()> {
-        DomoRequestBuilder::new(self.auth, "https://api.domo.com/v1/accounts/").into()
-    }
 }
 
 /// Request Builder for all Dataset API interactions
@@ -302,11 +370,15 @@ pub trait DomoRequest<T>: BaseRequest {
     }
 
     fn send_csv(&self) -> Result<reqwest::Response, PitchforkError> {
+        self.send_csv_body(self.body().take().unwrap_or_default().into())
+    }
+    /// Send csv data from a `reqwest::Body`, which can stream from a reader.
+    fn send_csv_body(&self, body: reqwest::Body) -> Result<reqwest::Response, PitchforkError> {
         let mut response = CLIENT
             .request(self.method(), self.url())
             .bearer_auth(self.auth())
             .header("Content-Type", "text/csv")
-            .body(self.body().take().unwrap_or_default())
+            .body(body)
             .send()?;
         if response.status().is_success() {
             Ok(response)
@@ -1,2 +1,10 @@
 /// Csv Helper
 pub mod csv;
+/// Progress reporting for uploads and downloads
+pub mod progress;
+/// Schema inference from Csv data
+pub mod schema;
+/// SQL Helpers for Domo Dataset queries
+pub mod sql;
+/// Pre-upload validation of data against a Dataset schema
+pub mod validate;
@@ -0,0 +1,391 @@
+use crate::domo::stream::split::split_csv;
+use log::info;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Min time between two byte progress reports while data is streaming.
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Receives progress updates of an upload or download.
+///
+/// Updates can come from several threads at once when parts are uploaded in parallel.
+///
+/// # Example
+/// ```no_run
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::domo::stream::UpdateMethod;
+/// use domo_pitchfork::pitchfork::DomoPitchfork;
+/// use domo_pitchfork::util::progress::{Progress, ProgressReporter};
+/// use std::sync::Arc;
+/// struct PrintProgress;
+/// impl ProgressReporter for PrintProgress {
+///     fn on_progress(&self, progress: &Progress) {
+///         println!("{}", progress);
+///     }
+/// }
+/// let domo = DomoPitchfork::with_token("token");
+/// let csv = std::fs::read_to_string("orders.csv")?;
+/// domo.datasets().upload_from_str_with_progress(
+///     "ds_id",
+///     csv,
+///     &UpdateMethod::Replace,
+///     Arc::new(PrintProgress),
+/// )?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub trait ProgressReporter: Send + Sync {
+    /// Called as data is sent or received and when a part completes.
+    fn on_progress(&self, progress: &Progress);
+
+    /// Called once when the transfer has completed successfully.
+    fn on_finish(&self, progress: &Progress) {
+        self.on_progress(progress);
+    }
+}
+
+impl fmt::Debug for dyn ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ProgressReporter")
+    }
+}
+
+/// Snapshot of a transfer's progress.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// Bytes sent or received so far.
+    pub bytes: u64,
+    /// Total bytes of the transfer, if known.
+    pub total_bytes: Option<u64>,
+    /// Csv rows in the completed parts.
+    pub rows: u64,
+    /// Completed parts.
+    pub parts: u32,
+    /// Total number of parts, if known.
+    pub total_parts: Option<u32>,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Average bytes per second since the transfer started.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Completed fraction between 0 and 1, from bytes or else parts, if the total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        match (self.total_bytes, self.total_parts) {
+            (Some(total), _) if total > 0 => Some((self.bytes as f64 / total as f64).min(1.0)),
+            (_, Some(total)) if total > 0 => {
+                Some((f64::from(self.parts) / f64::from(total)).min(1.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Estimated time left at the average rate so far, if the total is known.
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction()?;
+        if fraction <= 0.0 {
+            return None;
+        }
+        let secs = self.elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
+        Some(Duration::from_secs_f64(secs))
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_bytes(self.bytes as f64))?;
+        if let Some(total) = self.total_bytes {
+            write!(f, " of {}", format_bytes(total as f64))?;
+        }
+        write!(f, ", {} rows, {}", self.rows, self.parts)?;
+        if let Some(total) = self.total_parts {
+            write!(f, "/{}", total)?;
+        }
+        write!(f, " parts, {}/s", format_bytes(self.throughput()))?;
+        if let Some(eta) = self.eta() {
+            write!(f, ", ETA {}s", eta.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// A [`ProgressReporter`] that logs the progress at `info` level at most once per interval.
+#[derive(Debug)]
+pub struct LogProgress {
+    label: String,
+    interval: Duration,
+    last_logged: Mutex<Option<Instant>>,
+}
+
+impl LogProgress {
+    pub fn new(label: &str, interval: Duration) -> Self {
+        Self {
+            label: label.to_string(),
+            interval,
+            last_logged: Mutex::new(None),
+        }
+    }
+}
+
+impl ProgressReporter for LogProgress {
+    fn on_progress(&self, progress: &Progress) {
+        let mut last = self
+            .last_logged
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if last.map_or(true, |t| t.elapsed() >= self.interval) {
+            *last = Some(Instant::now());
+            info!("{}: {}", self.label, progress);
+        }
+    }
+
+    fn on_finish(&self, progress: &Progress) {
+        info!("{} finished: {}", self.label, progress);
+    }
+}
+
+/// Tracks a transfer and reports it to a [`ProgressReporter`].
+///
+/// Create one with the totals of a transfer that spans several calls, like the parts of a
+/// Stream execution uploaded one by one, and pass it to each call.
+///
+/// # Example
+/// ```no_run
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::pitchfork::DomoPitchfork;
+/// use domo_pitchfork::util::progress::{LogProgress, ProgressTracker};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// let domo = DomoPitchfork::with_token("token");
+/// let parts = vec!["a,1\n".to_string(), "b,2\n".to_string()];
+/// let total_bytes = parts.iter().map(|p| p.len() as u64).sum();
+/// let tracker = Arc::new(ProgressTracker::new(
+///     Arc::new(LogProgress::new("orders", Duration::from_secs(10))),
+///     Some(total_bytes),
+///     Some(parts.len() as u32),
+/// ));
+/// let execution = domo.streams().create_stream_execution(123)?;
+/// for (i, part) in parts.into_iter().enumerate() {
+///     domo.streams()
+///         .upload_part_with_progress(123, execution.id, i as u32 + 1, part, &tracker)?;
+/// }
+/// domo.streams().commit_execution(123, execution.id)?;
+/// tracker.finish();
+/// # Ok::<(), PitchforkError>(())
+/// ```
+pub struct ProgressTracker {
+    reporter: Arc<dyn ProgressReporter>,
+    start: Instant,
+    state: Mutex<(Progress, Option<Instant>)>,
+}
+
+impl fmt::Debug for ProgressTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressTracker")
+            .field("progress", &self.lock().0)
+            .finish()
+    }
+}
+
+impl ProgressTracker {
+    /// Track a transfer of `total_bytes` in `total_parts` parts, if they're known.
+    pub fn new(
+        reporter: Arc<dyn ProgressReporter>,
+        total_bytes: Option<u64>,
+        total_parts: Option<u32>,
+    ) -> Self {
+        Self {
+            reporter,
+            start: Instant::now(),
+            state: Mutex::new((
+                Progress {
+                    total_bytes,
+                    total_parts,
+                    ..Progress::default()
+                },
+                None,
+            )),
+        }
+    }
+
+    /// Count bytes sent or received. Reports are throttled to one per `REPORT_INTERVAL`.
+    pub(crate) fn add_bytes(&self, bytes: u64) {
+        let progress = {
+            let mut state = self.lock();
+            state.0.bytes += bytes;
+            if matches!(state.1, Some(t) if t.elapsed() < REPORT_INTERVAL) {
+                return;
+            }
+            state.1 = Some(Instant::now());
+            state.0.elapsed = self.start.elapsed();
+            state.0.clone()
+        };
+        self.reporter.on_progress(&progress);
+    }
+
+    /// Count a completed part and report it.
+    pub(crate) fn part_done(&self, rows: u64) {
+        let progress = {
+            let mut state = self.lock();
+            state.0.parts += 1;
+            state.0.rows += rows;
+            state.0.elapsed = self.start.elapsed();
+            state.0.clone()
+        };
+        self.reporter.on_progress(&progress);
+    }
+
+    /// Report the transfer as finished.
+    pub fn finish(&self) {
+        let progress = {
+            let mut state = self.lock();
+            state.0.elapsed = self.start.elapsed();
+            state.0.clone()
+        };
+        self.reporter.on_finish(&progress);
+    }
+
+    // reporters are called after the lock is released, so they can't block other parts
+    // and can use the tracker themselves
+    fn lock(&self) -> std::sync::MutexGuard<'_, (Progress, Option<Instant>)> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Counts the bytes read through it, e.g. a request or response body.
+pub(crate) struct ProgressReader<R> {
+    inner: R,
+    tracker: Arc<ProgressTracker>,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub(crate) fn new(inner: R, tracker: Arc<ProgressTracker>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.tracker.add_bytes(n as u64);
+        Ok(n)
+    }
+}
+
+/// Number of csv rows in `data`, counting a line break inside quotes as part of the row.
+pub(crate) fn csv_rows(data: &[u8]) -> u64 {
+    split_csv(data, u64::MAX, false)
+        .map(|parts| parts.iter().map(|p| p.rows).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Progress>>);
+
+    impl ProgressReporter for Recorder {
+        fn on_progress(&self, progress: &Progress) {
+            self.0.lock().unwrap().push(progress.clone());
+        }
+    }
+
+    #[test]
+    fn test_progress_eta() {
+        let progress = Progress {
+            bytes: 2048,
+            total_bytes: Some(8192),
+            rows: 10,
+            parts: 1,
+            total_parts: Some(4),
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(progress.throughput(), 1024.0);
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+        assert_eq!(
+            progress.to_string(),
+            "2.0 KB of 8.0 KB, 10 rows, 1/4 parts, 1.0 KB/s, ETA 6s"
+        );
+        let unknown = Progress {
+            parts: 3,
+            ..Progress::default()
+        };
+        assert_eq!(unknown.eta(), None);
+        assert_eq!(unknown.to_string(), "0 B, 0 rows, 3 parts, 0 B/s");
+    }
+
+    #[test]
+    fn test_progress_reader() {
+        let recorder = Arc::new(Recorder::default());
+        let tracker = Arc::new(ProgressTracker::new(recorder.clone(), Some(8), Some(1)));
+        let mut reader = ProgressReader::new("a,1\nb,2\n".as_bytes(), Arc::clone(&tracker));
+        let mut out = String::new();
+        reader.read_to_string(&mut out).unwrap();
+        tracker.part_done(csv_rows(out.as_bytes()));
+        tracker.finish();
+
+        let reports = recorder.0.lock().unwrap();
+        // the first read is reported, the rest are throttled
+        assert_eq!(reports.len(), 3);
+        assert!(reports[0].bytes > 0);
+        let last = reports.last().unwrap();
+        assert_eq!((last.bytes, last.rows, last.parts), (8, 2, 1));
+        assert_eq!(last.fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn test_reporter_can_use_tracker() {
+        // a reporter reading the tracker would deadlock if it were called under the lock
+        #[derive(Default)]
+        struct Nested(Mutex<Option<Arc<ProgressTracker>>>, Mutex<Vec<String>>);
+
+        impl ProgressReporter for Nested {
+            fn on_progress(&self, _: &Progress) {
+                if let Some(tracker) = self.0.lock().unwrap().as_ref() {
+                    self.1.lock().unwrap().push(format!("{:?}", tracker));
+                }
+            }
+        }
+
+        let nested = Arc::new(Nested::default());
+        let tracker = Arc::new(ProgressTracker::new(nested.clone(), None, Some(2)));
+        *nested.0.lock().unwrap() = Some(Arc::clone(&tracker));
+        tracker.add_bytes(4);
+        tracker.part_done(1);
+        assert_eq!(nested.1.lock().unwrap().len(), 2);
+        nested.0.lock().unwrap().take();
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        assert_eq!(csv_rows(b"a,\"x\ny\"\nb,2"), 2);
+        assert_eq!(csv_rows(b""), 0);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::domo::dataset::{DatasetSchema, DomoDataType, FieldType, Schema};
+use crate::error::PitchforkError;
+use std::io::Read;
+
+/// Options for [`infer_schema`].
+#[derive(Clone, Debug)]
+pub struct InferSchemaOptions {
+    /// Number of data rows to sample. `None` reads the whole file.
+    pub sample_rows: Option<usize>,
+    pub delimiter: u8,
+    /// Max number of values kept per column that downgraded the column to STRING.
+    pub max_downgrade_examples: usize,
+}
+
+impl Default for InferSchemaOptions {
+    fn default() -> Self {
+        Self {
+            sample_rows: Some(1000),
+            delimiter: b',',
+            max_downgrade_examples: 5,
+        }
+    }
+}
+
+impl InferSchemaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sample_rows(mut self, rows: usize) -> Self {
+        self.sample_rows = Some(rows);
+        self
+    }
+
+    pub fn whole_file(mut self) -> Self {
+        self.sample_rows = None;
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn max_downgrade_examples(mut self, max: usize) -> Self {
+        self.max_downgrade_examples = max;
+        self
+    }
+}
+
+/// Inferred type of a single column.
+#[derive(Clone, Debug)]
+pub struct ColumnInference {
+    pub name: String,
+    pub field_type: FieldType,
+    /// Number of sampled values that weren't empty.
+    pub non_null: usize,
+    /// Number of sampled values that were empty.
+    pub nulls: usize,
+    /// Share (0.0 - 1.0) of the non empty values that look like `field_type`.
+    /// A STRING column with a low confidence had mostly typed values and was
+    /// downgraded by a few values listed in `downgraded_by`.
+    pub confidence: f64,
+    /// Sample of the values that downgraded a typed column to STRING.
+    pub downgraded_by: Vec<String>,
+}
+
+impl ColumnInference {
+    pub fn domo_type(&self) -> DomoDataType {
+        DomoDataType::from_fieldtype(self.field_type)
+    }
+}
+
+/// Result of [`infer_schema`] with the inferred columns in header order.
+#[derive(Clone, Debug)]
+pub struct SchemaInference {
+    pub columns: Vec<ColumnInference>,
+    /// Number of data rows that were sampled.
+    pub rows_sampled: usize,
+}
+
+impl SchemaInference {
+    pub fn schema(&self) -> Schema {
+        let cols: Vec<(String, FieldType)> = self
+            .columns
+            .iter()
+            .map(|c| (c.name.clone(), c.field_type))
+            .collect();
+        Schema::from_field_types(&cols)
+    }
+
+    pub fn dataset_schema(&self, name: &str, description: &str) -> DatasetSchema {
+        DatasetSchema {
+            name: name.to_string(),
+            description: description.to_string(),
+            rows: 0,
+            schema: self.schema(),
+        }
+    }
+}
+
+/// Infer a Domo Schema from csv data with a header row.
+/// Columns keep the order of the header.
+///
+/// # Example
+/// ```
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::domo::dataset::DomoDataType;
+/// use domo_pitchfork::util::schema::{infer_schema, InferSchemaOptions};
+/// let csv = "id,amount,day\n1,1.5,2019-07-10\n2,n/a,2019-07-11\n";
+/// let inferred = infer_schema(csv.as_bytes(), &InferSchemaOptions::new())?;
+/// let ds_schema = inferred.dataset_schema("Orders", "Orders from the shop");
+/// assert_eq!(ds_schema.schema.columns[1].column_type, DomoDataType::STRING);
+/// assert_eq!(inferred.columns[1].downgraded_by, vec!["n/a"]);
+/// # Ok::<(), PitchforkError>(())
+/// ```
+pub fn infer_schema<R: Read>(
+    reader: R,
+    options: &InferSchemaOptions,
+) -> Result<SchemaInference, PitchforkError> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(true)
+        .from_reader(reader);
+    let mut columns: Vec<ColumnState> = rdr
+        .byte_headers()?
+        .iter()
+        .map(|h| ColumnState::new(String::from_utf8_lossy(h).into_owned()))
+        .collect();
+
+    let mut rows_sampled = 0;
+    let mut record = csv::ByteRecord::new();
+    while options.sample_rows.map_or(true, |max| rows_sampled < max)
+        && rdr.read_byte_record(&mut record)?
+    {
+        for (col, value) in columns.iter_mut().zip(record.iter()) {
+            col.add_sample(value, options.max_downgrade_examples);
+        }
+        rows_sampled += 1;
+    }
+
+    Ok(SchemaInference {
+        columns: columns
+            .into_iter()
+            .map(|c| c.finish(options.max_downgrade_examples))
+            .collect(),
+        rows_sampled,
+    })
+}
+
+struct ColumnState {
+    name: String,
+    field_type: FieldType,
+    non_null: usize,
+    nulls: usize,
+    /// count of values per sampled type, indexed by `type_index`
+    type_counts: [usize; 7],
+    /// values that changed a typed column to STRING when merged
+    transitions: Vec<String>,
+    /// values that look like plain strings
+    unicode_examples: Vec<String>,
+}
+
+impl ColumnState {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            field_type: FieldType::default(),
+            non_null: 0,
+            nulls: 0,
+            type_counts: [0; 7],
+            transitions: Vec::new(),
+            unicode_examples: Vec::new(),
+        }
+    }
+
+    fn add_sample(&mut self, value: &[u8], max_examples: usize) {
+        let typ = FieldType::from_sample(value);
+        if typ.is_null() {
+            self.nulls += 1;
+            return;
+        }
+        self.non_null += 1;
+        self.type_counts[type_index(typ)] += 1;
+        let before = self.field_type;
+        self.field_type.merge(typ);
+        let downgraded = self.field_type == FieldType::TUnicode
+            && before != FieldType::TUnicode
+            && !before.is_null();
+        if downgraded && self.transitions.len() < max_examples {
+            self.transitions
+                .push(String::from_utf8_lossy(value).into_owned());
+        }
+        if typ == FieldType::TUnicode && self.unicode_examples.len() < max_examples {
+            self.unicode_examples
+                .push(String::from_utf8_lossy(value).into_owned());
+        }
+    }
+
+    fn finish(self, max_examples: usize) -> ColumnInference {
+        let count = |types: &[FieldType]| -> usize {
+            types.iter().map(|t| self.type_counts[type_index(*t)]).sum()
+        };
+        let matching = match self.field_type {
+            FieldType::TFloat => count(&[FieldType::TFloat, FieldType::TInteger]),
+            FieldType::TDate => count(&[FieldType::TDate, FieldType::TDateTime]),
+            FieldType::TUnknown | FieldType::TNull => 0,
+            typ => count(&[typ]),
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let confidence = if self.non_null == 0 {
+            0.0
+        } else {
+            matching as f64 / self.non_null as f64
+        };
+        let saw_typed = self.non_null > count(&[FieldType::TUnicode]);
+        let mut downgraded_by = Vec::new();
+        if self.field_type == FieldType::TUnicode && saw_typed {
+            for v in self.transitions.into_iter().chain(self.unicode_examples) {
+                if downgraded_by.len() < max_examples && !downgraded_by.contains(&v) {
+                    downgraded_by.push(v);
+                }
+            }
+        }
+        ColumnInference {
+            name: self.name,
+            field_type: self.field_type,
+            non_null: self.non_null,
+            nulls: self.nulls,
+            confidence,
+            downgraded_by,
+        }
+    }
+}
+
+fn type_index(typ: FieldType) -> usize {
+    match typ {
+        FieldType::TUnknown => 0,
+        FieldType::TNull => 1,
+        FieldType::TUnicode => 2,
+        FieldType::TFloat => 3,
+        FieldType::TInteger => 4,
+        FieldType::TDate => 5,
+        FieldType::TDateTime => 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_schema_keeps_header_order() {
+        let csv = "zeta,alpha,mid,when,at,empty\n1,a,1,2019-07-10,2019-07-10T16:39:57Z,\n2,b,1.5,7/11/2019,2019-07-10T16:39:57Z,\n";
+        let inferred = infer_schema(csv.as_bytes(), &InferSchemaOptions::new()).unwrap();
+        let schema = inferred.schema();
+        let cols: Vec<(&str, &str)> = schema
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.column_type.as_str()))
+            .collect();
+        assert_eq!(
+            cols,
+            vec![
+                ("zeta", "LONG"),
+                ("alpha", "STRING"),
+                ("mid", "DECIMAL"),
+                ("when", "DATE"),
+                ("at", "DATETIME"),
+                ("empty", "STRING")
+            ]
+        );
+        assert_eq!(inferred.rows_sampled, 2);
+        assert_eq!(inferred.columns[5].nulls, 2);
+        assert!(inferred.columns[5].confidence.abs() < std::f64::EPSILON);
+        assert!((inferred.columns[1].confidence - 1.0).abs() < std::f64::EPSILON);
+        assert!(inferred.columns[1].downgraded_by.is_empty());
+    }
+
+    #[test]
+    fn test_infer_schema_reports_downgrades() {
+        let csv = "a,b\noops,1\n1,2\n2,2019-07-10\n3,4\n";
+        let inferred = infer_schema(csv.as_bytes(), &InferSchemaOptions::new()).unwrap();
+        let a = &inferred.columns[0];
+        assert_eq!(a.field_type, FieldType::TUnicode);
+        assert_eq!(a.downgraded_by, vec!["oops"]);
+        assert!((a.confidence - 0.25).abs() < std::f64::EPSILON);
+        let b = &inferred.columns[1];
+        assert_eq!(b.field_type, FieldType::TUnicode);
+        assert_eq!(b.downgraded_by, vec!["2019-07-10"]);
+        assert!(b.confidence.abs() < std::f64::EPSILON);
+    }
+
+    #[test]
+    fn test_infer_schema_sample_rows() {
+        let csv = "a\n1\n2\nthree\n";
+        let sampled =
+            infer_schema(csv.as_bytes(), &InferSchemaOptions::new().sample_rows(2)).unwrap();
+        assert_eq!(sampled.rows_sampled, 2);
+        assert_eq!(sampled.columns[0].field_type, FieldType::TInteger);
+        let whole = infer_schema(csv.as_bytes(), &InferSchemaOptions::new().whole_file()).unwrap();
+        assert_eq!(whole.rows_sampled, 3);
+        assert_eq!(whole.columns[0].field_type, FieldType::TUnicode);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::error::PitchforkError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+/// A value bound to a `?` placeholder by [`bind_params`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SqlParam {
+    Null,
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    /// A comma separated list for use with `IN ?`. The parentheses are added, so don't
+    /// write `IN (?)`. Can't be empty or nested.
+    List(Vec<SqlParam>),
+}
+
+impl SqlParam {
+    /// Render the param as a Domo SQL literal.
+    pub fn to_sql(&self) -> Result<String, PitchforkError> {
+        match self {
+            SqlParam::Null => Ok("NULL".to_string()),
+            SqlParam::Str(s) => Ok(quote_literal(s)),
+            SqlParam::Int(i) => Ok(i.to_string()),
+            SqlParam::Float(f) if f.is_finite() => Ok(f.to_string()),
+            SqlParam::Float(f) => Err(PitchforkError::new(format!(
+                "{} can't be used as a SQL param",
+                f
+            ))),
+            SqlParam::Date(d) => Ok(quote_literal(&d.format("%Y-%m-%d").to_string())),
+            SqlParam::DateTime(dt) => {
+                Ok(quote_literal(&dt.format("%Y-%m-%d %H:%M:%S").to_string()))
+            }
+            SqlParam::List(values) => {
+                if values.is_empty() {
+                    return Err(PitchforkError::new("SQL list params can't be empty"));
+                }
+                let mut items = Vec::with_capacity(values.len());
+                for v in values {
+                    if let SqlParam::List(_) = v {
+                        return Err(PitchforkError::new("SQL list params can't be nested"));
+                    }
+                    items.push(v.to_sql()?);
+                }
+                Ok(format!("({})", items.join(", ")))
+            }
+        }
+    }
+}
+
+impl From<&str> for SqlParam {
+    fn from(s: &str) -> Self {
+        SqlParam::Str(s.to_string())
+    }
+}
+
+impl From<String> for SqlParam {
+    fn from(s: String) -> Self {
+        SqlParam::Str(s)
+    }
+}
+
+impl From<i64> for SqlParam {
+    fn from(i: i64) -> Self {
+        SqlParam::Int(i)
+    }
+}
+
+impl From<i32> for SqlParam {
+    fn from(i: i32) -> Self {
+        SqlParam::Int(i64::from(i))
+    }
+}
+
+impl From<u32> for SqlParam {
+    fn from(i: u32) -> Self {
+        SqlParam::Int(i64::from(i))
+    }
+}
+
+impl From<f64> for SqlParam {
+    fn from(f: f64) -> Self {
+        SqlParam::Float(f)
+    }
+}
+
+impl From<NaiveDate> for SqlParam {
+    fn from(d: NaiveDate) -> Self {
+        SqlParam::Date(d)
+    }
+}
+
+impl From<NaiveDateTime> for SqlParam {
+    fn from(dt: NaiveDateTime) -> Self {
+        SqlParam::DateTime(dt)
+    }
+}
+
+impl From<DateTime<Utc>> for SqlParam {
+    fn from(dt: DateTime<Utc>) -> Self {
+        SqlParam::DateTime(dt.naive_utc())
+    }
+}
+
+impl<T: Into<SqlParam>> From<Option<T>> for SqlParam {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(SqlParam::Null, Into::into)
+    }
+}
+
+impl<T: Into<SqlParam>> From<Vec<T>> for SqlParam {
+    fn from(values: Vec<T>) -> Self {
+        SqlParam::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Quote a column name as a Domo SQL identifier, e.g. `` `Order Priority` ``.
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Quote a string as a Domo SQL string literal, escaping quotes and backslashes.
+pub fn quote_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// Replace each `?` placeholder in `sql` with the next param rendered as a SQL literal.
+/// Question marks inside quoted strings and identifiers are left alone.
+///
+/// # Example
+/// ```
+/// # use domo_pitchfork::error::PitchforkError;
+/// use domo_pitchfork::util::sql::{bind_params, SqlParam};
+/// let sql = bind_params(
+///     "SELECT * FROM table WHERE `Region` = ? AND `Year` IN ?",
+///     &["O'Brien".into(), vec![2018, 2019].into()],
+/// )?;
+/// assert_eq!(
+///     sql,
+///     "SELECT * FROM table WHERE `Region` = 'O''Brien' AND `Year` IN (2018, 2019)"
+/// );
+/// # Ok::<(), PitchforkError>(())
+/// ```
+pub fn bind_params(sql: &str, params: &[SqlParam]) -> Result<String, PitchforkError> {
+    let mut out = String::with_capacity(sql.len());
+    let mut params_iter = params.iter();
+    let mut quote: Option<char> = None;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                out.push(c);
+                if c == '\\' && q != '`' {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                } else if c == q {
+                    // a doubled quote char is an escaped quote, not the end of the string
+                    if chars.peek() == Some(&q) {
+                        out.push(q);
+                        chars.next();
+                    } else {
+                        quote = None;
+                    }
+                }
+            }
+            None => match c {
+                '\'' | '"' | '`' => {
+                    quote = Some(c);
+                    out.push(c);
+                }
+                '?' => match params_iter.next() {
+                    Some(p) => out.push_str(&p.to_sql()?),
+                    None => {
+                        return Err(PitchforkError::new(format!(
+                            "SQL has more placeholders than the {} params provided",
+                            params.len()
+                        )))
+                    }
+                },
+                _ => out.push(c),
+            },
+        }
+    }
+    if params_iter.next().is_some() {
+        return Err(PitchforkError::new(format!(
+            "SQL has fewer placeholders than the {} params provided",
+            params.len()
+        )));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("Order Priority"), "`Order Priority`");
+        assert_eq!(quote_identifier("a`b"), "`a``b`");
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("High"), "'High'");
+        assert_eq!(quote_literal("O'Brien"), "'O''Brien'");
+        assert_eq!(quote_literal("a\\' OR 1=1"), "'a\\\\'' OR 1=1'");
+    }
+
+    #[test]
+    fn test_bind_params() {
+        let date = NaiveDate::from_ymd_opt(2019, 7, 10).unwrap();
+        let sql = bind_params(
+            "SELECT `why?` FROM table WHERE `a` = ? AND b = 'what?' AND c IN ? AND d > ? AND e = ?",
+            &[
+                "x'; DROP".into(),
+                vec!["a", "b"].into(),
+                date.into(),
+                1.5.into(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT `why?` FROM table WHERE `a` = 'x''; DROP' AND b = 'what?' AND c IN ('a', 'b') AND d > '2019-07-10' AND e = 1.5"
+        );
+    }
+
+    #[test]
+    fn test_bind_params_escaped_quotes() {
+        let sql = bind_params("SELECT 'it''s ?', 'a\\'?' FROM t WHERE x = ?", &[1.into()]).unwrap();
+        assert_eq!(sql, "SELECT 'it''s ?', 'a\\'?' FROM t WHERE x = 1");
+    }
+
+    #[test]
+    fn test_bind_params_count_mismatch() {
+        assert!(bind_params("SELECT ? FROM t", &[]).is_err());
+        assert!(bind_params("SELECT 1 FROM t", &[1.into()]).is_err());
+        assert!(bind_params("SELECT ? FROM t", &[SqlParam::List(vec![])]).is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use crate::domo::dataset::{DomoDataType, Schema};
+use crate::error::PitchforkError;
+use crate::util::csv::serialize_to_csv_str;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+
+/// Options for [`validate_csv`].
+#[derive(Clone, Debug)]
+pub struct ValidateOptions {
+    /// Whether the first row is a header row that should be skipped.
+    pub has_headers: bool,
+    /// Stop validating after this many issues. `None` checks all rows.
+    pub max_issues: Option<usize>,
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        Self {
+            has_headers: false,
+            max_issues: Some(100),
+        }
+    }
+}
+
+impl ValidateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    pub fn max_issues(mut self, max: usize) -> Self {
+        self.max_issues = Some(max);
+        self
+    }
+
+    pub fn all_issues(mut self) -> Self {
+        self.max_issues = None;
+        self
+    }
+}
+
+/// What is wrong with a value or row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssueKind {
+    /// The row doesn't have one value per schema column.
+    ColumnCount { expected: usize, found: usize },
+    /// The value can't be parsed as the column's Domo type.
+    InvalidValue(DomoDataType),
+    /// The value contains a line break, which Domo rejects in imported data.
+    EmbeddedNewline,
+    /// The row couldn't be read as csv.
+    Csv(String),
+}
+
+/// A single problem found by the validator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// 1 based data row number, not counting the header row.
+    pub row: usize,
+    /// 1 based line of the csv text the row starts on.
+    pub line: u64,
+    /// 0 based column index for value issues.
+    pub column: Option<usize>,
+    pub column_name: Option<String>,
+    pub value: Option<String>,
+    pub kind: ValidationIssueKind,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {} (line {})", self.row, self.line)?;
+        if let Some(name) = &self.column_name {
+            write!(f, ", column '{}'", name)?;
+        }
+        match &self.kind {
+            ValidationIssueKind::ColumnCount { expected, found } => {
+                write!(f, ": expected {} values, found {}", expected, found)
+            }
+            ValidationIssueKind::InvalidValue(typ) => write!(
+                f,
+                ": '{}' is not a valid {}",
+                self.value.as_ref().map_or("", String::as_str),
+                typ
+            ),
+            ValidationIssueKind::EmbeddedNewline => write!(f, ": value contains a line break"),
+            ValidationIssueKind::Csv(msg) => write!(f, ": {}", msg),
+        }
+    }
+}
+
+/// Result of validating data against a [`Schema`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+    /// Number of data rows that were checked.
+    pub rows_checked: usize,
+    /// Whether validation stopped early because `max_issues` was reached.
+    pub truncated: bool,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Turn an invalid report into a `PitchforkErrorKind::Validation` error.
+    pub fn into_result(self) -> Result<(), PitchforkError> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(self.into())
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "{} rows are valid", self.rows_checked);
+        }
+        write!(
+            f,
+            "{}{} issues in {} rows",
+            self.issues.len(),
+            if self.truncated { "+" } else { "" },
+            self.rows_checked
+        )?;
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ValidationReport {}
+
+/// Check csv data against a Domo [`Schema`] before uploading it.
+///
+/// Checks that each row has a value for every column, that `LONG`, `DECIMAL` and `DOUBLE`
+/// values are numbers, that `DATE` and `DATETIME` values are dates Domo can import and that
+/// no value contains a line break. Empty values are treated as nulls and are always valid.
+///
+/// # Example
+/// ```
+/// use domo_pitchfork::domo::dataset::{FieldType, Schema};
+/// use domo_pitchfork::util::validate::{validate_csv, ValidateOptions};
+/// let schema = Schema::from_field_types(&[
+///     ("id".to_string(), FieldType::TInteger),
+///     ("day".to_string(), FieldType::TDate),
+/// ]);
+/// let report = validate_csv("1,2019-07-10\ntwo,2019-07-11\n", &schema, &ValidateOptions::new());
+/// assert!(!report.is_valid());
+/// assert_eq!(report.issues[0].row, 2);
+/// assert_eq!(report.issues[0].column_name.as_ref().unwrap(), "id");
+/// ```
+pub fn validate_csv(csv: &str, schema: &Schema, options: &ValidateOptions) -> ValidationReport {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(options.has_headers)
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    let expected = schema.columns.len();
+    let mut report = ValidationReport::default();
+    let mut record = csv::StringRecord::new();
+    loop {
+        let limit_reached = options
+            .max_issues
+            .map_or(false, |max| report.issues.len() >= max);
+        let row = report.rows_checked + 1;
+        match rdr.read_record(&mut record) {
+            Ok(false) => break,
+            // only truncated if there is another row that wasn't checked
+            Ok(true) | Err(_) if limit_reached => {
+                report.truncated = true;
+                break;
+            }
+            Ok(true) => {}
+            Err(e) => {
+                let line = e.position().map_or(0, csv::Position::line);
+                report.issues.push(ValidationIssue {
+                    row,
+                    line,
+                    column: None,
+                    column_name: None,
+                    value: None,
+                    kind: ValidationIssueKind::Csv(e.to_string()),
+                });
+                report.rows_checked += 1;
+                // the reader can't continue after an io error
+                if !e.is_io_error() {
+                    continue;
+                }
+                break;
+            }
+        }
+        report.rows_checked += 1;
+        let line = record.position().map_or(0, csv::Position::line);
+        if record.len() != expected {
+            report.issues.push(ValidationIssue {
+                row,
+                line,
+                column: None,
+                column_name: None,
+                value: None,
+                kind: ValidationIssueKind::ColumnCount {
+                    expected,
+                    found: record.len(),
+                },
+            });
+            continue;
+        }
+        for (i, (value, col)) in record.iter().zip(&schema.columns).enumerate() {
+            let kind = if value.contains('\n') || value.contains('\r') {
+                ValidationIssueKind::EmbeddedNewline
+            } else if !is_valid_value(&col.column_type, value) {
+                ValidationIssueKind::InvalidValue(col.column_type.clone())
+            } else {
+                continue;
+            };
+            report.issues.push(ValidationIssue {
+                row,
+                line,
+                column: Some(i),
+                column_name: Some(col.name.clone()),
+                value: Some(value.to_string()),
+                kind,
+            });
+        }
+    }
+    if let Some(max) = options.max_issues {
+        if report.issues.len() > max {
+            report.issues.truncate(max);
+            report.truncated = true;
+        }
+    }
+    report
+}
+
+/// Check `Serialize` records against a Domo [`Schema`]. See [`validate_csv`].
+pub fn validate_serializable<T: Serialize>(
+    data: &[T],
+    schema: &Schema,
+    options: &ValidateOptions,
+) -> Result<ValidationReport, PitchforkError> {
+    let csv = serialize_to_csv_str(data, false)?;
+    let options = options.clone().has_headers(false);
+    Ok(validate_csv(&csv, schema, &options))
+}
+
+fn is_valid_value(typ: &DomoDataType, value: &str) -> bool {
+    if value.is_empty() {
+        return true;
+    }
+    match typ {
+        DomoDataType::LONG => value.parse::<i64>().is_ok(),
+        DomoDataType::DECIMAL | DomoDataType::DOUBLE => {
+            value.parse::<f64>().map_or(false, f64::is_finite)
+        }
+        DomoDataType::DATE | DomoDataType::DATETIME => is_domo_date(value),
+        DomoDataType::STRING | DomoDataType::Unknown(_) => true,
+    }
+}
+
+/// Dates and datetimes in the formats Domo imports.
+fn is_domo_date(value: &str) -> bool {
+    value.parse::<DateTime<FixedOffset>>().is_ok()
+        || value.parse::<NaiveDateTime>().is_ok()
+        || NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").is_ok()
+        || value.parse::<NaiveDate>().is_ok()
+        || NaiveDate::parse_from_str(value, "%m/%d/%Y").is_ok()
+        || NaiveDate::parse_from_str(value, "%D").is_ok()
+        || NaiveDate::parse_from_str(value, "%v").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domo::dataset::FieldType;
+    use crate::error::PitchforkErrorKind;
+
+    fn schema() -> Schema {
+        Schema::from_field_types(&[
+            ("id".to_string(), FieldType::TInteger),
+            ("amount".to_string(), FieldType::TFloat),
+            ("day".to_string(), FieldType::TDate),
+            ("at".to_string(), FieldType::TDateTime),
+            ("note".to_string(), FieldType::TUnicode),
+        ])
+    }
+
+    #[test]
+    fn test_validate_csv_valid() {
+        let csv = "1,1.5,2019-07-10,2019-07-10T16:39:57Z,hi\n2,,07/11/2019,2019-07-10 16:39:57,\n";
+        let report = validate_csv(csv, &schema(), &ValidateOptions::new());
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(report.rows_checked, 2);
+    }
+
+    #[test]
+    fn test_validate_csv_issues() {
+        let csv = "id,amount,day,at,note\n1.5,NaN,2019-13-01,yesterday,ok\n1,2\n3,4,2019-07-10,2019-07-10,\"multi\nline\"\n";
+        let options = ValidateOptions::new().has_headers(true);
+        let report = validate_csv(csv, &schema(), &options);
+        let kinds: Vec<(usize, Option<usize>, &ValidationIssueKind)> = report
+            .issues
+            .iter()
+            .map(|i| (i.row, i.column, &i.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    1,
+                    Some(0),
+                    &ValidationIssueKind::InvalidValue(DomoDataType::LONG)
+                ),
+                (
+                    1,
+                    Some(1),
+                    &ValidationIssueKind::InvalidValue(DomoDataType::DECIMAL)
+                ),
+                (
+                    1,
+                    Some(2),
+                    &ValidationIssueKind::InvalidValue(DomoDataType::DATE)
+                ),
+                (
+                    1,
+                    Some(3),
+                    &ValidationIssueKind::InvalidValue(DomoDataType::DATETIME)
+                ),
+                (
+                    2,
+                    None,
+                    &ValidationIssueKind::ColumnCount {
+                        expected: 5,
+                        found: 2
+                    }
+                ),
+                (3, Some(4), &ValidationIssueKind::EmbeddedNewline),
+            ]
+        );
+        assert_eq!(report.issues[0].line, 2);
+        assert_eq!(report.issues[5].line, 4);
+    }
+
+    #[test]
+    fn test_validate_csv_max_issues() {
+        let csv = "a,,,,\nb,,,,\nc,,,,\n";
+        let report = validate_csv(csv, &schema(), &ValidateOptions::new().max_issues(2));
+        assert_eq!(report.issues.len(), 2);
+        assert!(report.truncated);
+        match report.into_result().unwrap_err().kind {
+            PitchforkErrorKind::Validation(report) => assert_eq!(report.issues.len(), 2),
+            kind => panic!("expected a Validation error, got {:?}", kind),
+        }
+
+        // the last row produces the last allowed issue, nothing is left unchecked
+        let report = validate_csv(
+            "a,,,,\nb,,,,\n",
+            &schema(),
+            &ValidateOptions::new().max_issues(2),
+        );
+        assert_eq!(report.issues.len(), 2);
+        assert!(!report.truncated);
+    }
+
+    #[test]
+    fn test_validate_serializable() {
+        #[derive(Serialize)]
+        struct Row {
+            id: String,
+            amount: f64,
+            day: &'static str,
+            at: &'static str,
+            note: &'static str,
+        }
+        let rows = vec![Row {
+            id: "x".to_string(),
+            amount: 1.0,
+            day: "2019-07-10",
+            at: "",
+            note: "",
+        }];
+        let report = validate_serializable(&rows, &schema(), &ValidateOptions::new()).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].column_name.as_ref().unwrap(), "id");
+    }
+}
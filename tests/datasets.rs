@@ -2,6 +2,7 @@ extern crate domo_pitchfork;
 
 use domo_pitchfork::auth::DomoClientAppCredentials;
 use domo_pitchfork::domo::dataset::Dataset;
+use domo_pitchfork::domo::stream::UpdateMethod;
 use domo_pitchfork::DomoPitchfork;
 use domo_pitchfork::PitchforkErrorKind;
 use std::env;
@@ -98,12 +99,27 @@ fn test_replace_dataset_data() {
     let token = get_domo_token();
     let rusty_fork = DomoPitchfork::with_token(&token);
     let csv = create_test_csv();
-    let replace = rusty_fork
-        .datasets()
-        .upload_from_str("77faea51-68ab-4dd3-ae1a-8992bc1b58a8", csv);
+    let replace = rusty_fork.datasets().upload_from_str(
+        "77faea51-68ab-4dd3-ae1a-8992bc1b58a8",
+        csv,
+        &UpdateMethod::Replace,
+    );
     assert!(replace.is_ok());
 }
 
+#[test]
+fn test_append_dataset_data() {
+    let token = get_domo_token();
+    let rusty_fork = DomoPitchfork::with_token(&token);
+    let csv = create_test_csv();
+    let append = rusty_fork.datasets().upload_from_str(
+        "77faea51-68ab-4dd3-ae1a-8992bc1b58a8",
+        csv,
+        &UpdateMethod::Append,
+    );
+    assert!(append.is_ok());
+}
+
 fn create_test_csv() -> String {
     "Sample Data,0
 Test AB,1
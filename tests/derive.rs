@@ -0,0 +1,80 @@
+#![cfg(feature = "derive")]
+extern crate domo_pitchfork;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use domo_pitchfork::domo::dataset::{Column, DatasetSchema, DomoSchema};
+use serde::Serialize;
+
+#[allow(dead_code)]
+#[derive(Serialize, DomoSchema)]
+struct Order {
+    #[serde(rename = "Order Id")]
+    id: i64,
+    customer: String,
+    quantity: Option<u32>,
+    price: f64,
+    #[domo(type = "DECIMAL")]
+    discount: Option<f32>,
+    shipped: bool,
+    order_date: NaiveDate,
+    #[serde(rename(serialize = "Created At"))]
+    created_at: DateTime<Utc>,
+    updated_at: Option<NaiveDateTime>,
+    #[serde(skip)]
+    internal_note: String,
+    #[serde(skip_serializing)]
+    cache_key: u64,
+    #[domo(type = "STRING")]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, DomoSchema)]
+#[serde(rename_all = "camelCase")]
+struct Renamed {
+    first_name: String,
+    #[serde(rename = "LAST")]
+    last_name: String,
+}
+
+fn columns(schema: &[Column]) -> Vec<(&str, &str)> {
+    schema
+        .iter()
+        .map(|c| (c.name.as_str(), c.column_type.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_derive_domo_schema() {
+    let schema = Order::domo_schema();
+    assert_eq!(
+        columns(&schema.columns),
+        vec![
+            ("Order Id", "LONG"),
+            ("customer", "STRING"),
+            ("quantity", "LONG"),
+            ("price", "DOUBLE"),
+            ("discount", "DECIMAL"),
+            ("shipped", "STRING"),
+            ("order_date", "DATE"),
+            ("Created At", "DATETIME"),
+            ("updated_at", "DATETIME"),
+            ("tags", "STRING"),
+        ]
+    );
+}
+
+#[test]
+fn test_derive_domo_schema_rename_all() {
+    let schema = Renamed::domo_schema();
+    assert_eq!(
+        columns(&schema.columns),
+        vec![("firstName", "STRING"), ("LAST", "STRING")]
+    );
+}
+
+#[test]
+fn test_dataset_schema_from_domo_schema() {
+    let ds = DatasetSchema::from_domo_schema::<Renamed>("people", "people dataset");
+    assert_eq!(ds.name, "people");
+    assert_eq!(ds.schema, Renamed::domo_schema());
+}
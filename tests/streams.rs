@@ -2,10 +2,13 @@ extern crate domo_pitchfork;
 extern crate serde_json;
 
 use domo_pitchfork::auth::DomoClientAppCredentials;
-use domo_pitchfork::domo::dataset::{Column, DatasetSchema, Schema};
-use domo_pitchfork::domo::stream::{StreamDatasetSchema, StreamSearchQuery};
+use domo_pitchfork::domo::dataset::{Column, DatasetSchema, DomoDataType, Schema};
+use domo_pitchfork::domo::stream::{
+    ExecutionState, StreamDatasetSchema, StreamSearchQuery, UpdateMethod,
+};
 use domo_pitchfork::pitchfork::DomoPitchfork;
 use std::env;
+use std::time::Duration;
 
 #[test]
 fn test_list_search_by_dataset_id_params_are_not_ignored_by_domo() {
@@ -25,11 +28,11 @@ fn test_stream_e2e() {
     let domo = DomoPitchfork::with_token(&token);
     let csv = create_test_csv();
     let c = Column {
-        column_type: "STRING".to_string(),
+        column_type: DomoDataType::STRING,
         name: "column name".to_string(),
     };
     let c2 = Column {
-        column_type: "LONG".to_string(),
+        column_type: DomoDataType::LONG,
         name: "column name 2".to_string(),
     };
     let s = Schema {
@@ -43,7 +46,7 @@ fn test_stream_e2e() {
     };
     let stream_ds = StreamDatasetSchema {
         dataset_schema: ds,
-        update_method: "APPEND".to_string(),
+        update_method: UpdateMethod::Append,
     };
 
     //let new_stream_ds = NewStreamDataset {
@@ -62,6 +65,74 @@ fn test_stream_e2e() {
     assert_eq!(1, 1);
 }
 
+#[test]
+fn test_search_by_name_update_method_and_last_execution_state() {
+    let token = get_domo_token();
+    let domo = DomoPitchfork::with_token(&token);
+    // a quote and a backslash check the name escaping
+    let name = "Rusty Stream Search \"Test\" C:\\data".to_string();
+    let stream_ds = StreamDatasetSchema {
+        dataset_schema: DatasetSchema {
+            name: name.clone(),
+            description: "Rusty Stream search criteria test".to_string(),
+            rows: 0u32,
+            schema: Schema {
+                columns: vec![
+                    Column {
+                        column_type: DomoDataType::STRING,
+                        name: "column name".to_string(),
+                    },
+                    Column {
+                        column_type: DomoDataType::LONG,
+                        name: "column name 2".to_string(),
+                    },
+                ],
+            },
+        },
+        update_method: UpdateMethod::Append,
+    };
+    let stream = domo.streams().create(&stream_ds).unwrap();
+    let e = domo.streams().create_stream_execution(stream.id).unwrap();
+    domo.streams()
+        .upload_part(stream.id, e.id, 1u32, &create_test_csv())
+        .unwrap();
+    domo.streams().commit_execution(stream.id, e.id).unwrap();
+    domo.streams()
+        .wait_for_execution(
+            stream.id,
+            e.id,
+            Duration::from_secs(300),
+            Duration::from_secs(2),
+        )
+        .unwrap();
+
+    let found = |query: StreamSearchQuery| {
+        domo.streams()
+            .search(query)
+            .unwrap()
+            .iter()
+            .any(|s| s.id == stream.id)
+    };
+    let by_name = || StreamSearchQuery::DatasetName(name.clone());
+    let results = found(by_name());
+    let append = found(by_name().and(StreamSearchQuery::UpdateMethod(UpdateMethod::Append)));
+    let replace = found(by_name().and(StreamSearchQuery::UpdateMethod(UpdateMethod::Replace)));
+    let success = found(by_name().and(StreamSearchQuery::LastExecutionState(
+        ExecutionState::Success,
+    )));
+    let error = found(by_name().and(StreamSearchQuery::LastExecutionState(ExecutionState::Error)));
+    domo.streams().delete(stream.id).unwrap();
+
+    assert!(results, "dataSource.name didn't find the stream");
+    assert!(append, "updateMethod:APPEND didn't find the stream");
+    assert!(!replace, "updateMethod:REPLACE was ignored");
+    assert!(
+        success,
+        "lastExecution.currentState:SUCCESS didn't find the stream"
+    );
+    assert!(!error, "lastExecution.currentState:ERROR was ignored");
+}
+
 fn create_test_csv() -> String {
     "Sample Data,0
 Test AB,1